@@ -1,46 +1,48 @@
-_if = function(...) local __args = {...}
-if 2 == #__args then
-local body = __args[2]
-if true == __args[1] then
-return body()
-end
-end
-
-end
-
-prompt = function(...) local __args = {...}
-if 1 == #__args then
-local question = __args[1]
-return function(...) local __args = {...}
-print(question)
-return read()
-end
-
-end
-
-end
-
-respond = function(...) local __args = {...}
-if 1 == #__args then
-if "niels\n" == __args[1] then
-return "yes ok hello\n"
-end
-end
-
-if 1 == #__args then
-local n = __args[1]
-return ("u r not cool, " .. n)
-end
-
-end
-
-game = function(...) local __args = {...}
-who = prompt("who are you?")
-
-identity = who()
-
-print(respond(identity))
-return game()
-end
-
-game()
\ No newline at end of file
+local __if, prompt, respond, game
+
+__if = function(...)
+  local __args = {...}
+  if 2 == select("#", ...) then
+    local body = __args[2]
+    if true == __args[1] then
+      return body()
+    end
+  end
+end
+
+prompt = function(...)
+  local __args = {...}
+  if 1 == select("#", ...) then
+    local question = __args[1]
+    return function(...)
+      local __args = {...}
+      print(question)
+      return read()
+    end
+  end
+end
+
+respond = function(...)
+  local __args = {...}
+  if 1 == select("#", ...) then
+    if "niels" == __args[1] then
+      return "yes ok hello"
+    end
+  end
+  if 1 == select("#", ...) then
+    local n = __args[1]
+    return ("u r not cool, " .. n)
+  end
+end
+
+game = function(...)
+  local __args = {...}
+  local who = prompt("who are you?")
+  local identity = trim((function(__v) if __v == nil then return "" end return __v end)(who()))
+  print(respond(identity))
+  return game()
+end
+
+game()
+
+return {["if"] = __if, prompt = prompt, respond = respond, game = game}
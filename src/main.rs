@@ -8,12 +8,12 @@ use std::rc::Rc;
 
 use std::io;
 use std::io::BufRead;
+use std::io::IsTerminal;
 
 mod snail;
 use snail::*;
 
 use std::io::prelude::*;
-use std::error::Error;
 
 use std::fs;
 use std::fs::File;
@@ -35,25 +35,31 @@ fn add_global(sym: &SymTab, env: &TypeTab, name: &str, t: Type) {
 }
 
 fn add_lua_standard(sym: &SymTab, env: &TypeTab) {
-    add_global(sym, env, "print",    Type::Block(Rc::new(Type::Any)));
-    add_global(sym, env, "read",     Type::Block(Rc::new(Type::Str)));
-    add_global(sym, env, "trim",     Type::Block(Rc::new(Type::Str)));
-    add_global(sym, env, "tostring", Type::Block(Rc::new(Type::Str)));
+    add_global(sym, env, "print",    Type::Block(Rc::new(Signature::new(vec![Type::union(vec![Type::Str, Type::Num])], 1, false, Type::Any))));
+    add_global(sym, env, "read",     Type::Block(Rc::new(Signature::new(vec![], 0, false, Type::Option(Rc::new(Type::Str))))));
+    add_global(sym, env, "trim",     Type::Block(Rc::new(Signature::new(vec![Type::Str], 1, false, Type::Str))));
+    add_global(sym, env, "tostring", Type::Block(Rc::new(Signature::new(vec![Type::Any], 1, false, Type::Str))));
 }
 
-fn write_path(path: &str) {
-    let meta = metadata(path).unwrap();
+fn write_path(path: &str, options: &Options, diagnostics: &Rc<Diagnostics>) {
+    let meta = match metadata(path) {
+        Ok(m)    => m,
+        Err(why) => return diagnostics.report(Diagnostic::error("E0401", &format!("{}: {}", path, why))),
+    };
     
     if meta.is_file() {
-        match file(path) {
-            Some(n) => write(path, n),
+        match file(path, options, diagnostics) {
+            Some((lua, map)) => write(path, lua, map, diagnostics),
             None    => (),
         }
     } else {
-        let paths = fs::read_dir(path).unwrap();
+        let paths = match fs::read_dir(path) {
+            Ok(p)    => p,
+            Err(why) => return diagnostics.report(Diagnostic::error("E0401", &format!("{}: {}", path, why))),
+        };
 
-        for path in paths {
-            let path = format!("{}", path.unwrap().path().display());
+        for path in paths.filter_map(|p| p.ok()) {
+            let path = format!("{}", path.path().display());
             let split: Vec<&str> = path.split(".").collect();
 
             match split.get(1) {
@@ -61,43 +67,113 @@ fn write_path(path: &str) {
                 _ => continue,
             }
 
-            write_path(&format!("{}", path))
+            write_path(&format!("{}", path), options, diagnostics)
         }
     }
 }
 
-fn execute_path(path_str: &str) {
-    let meta = metadata(path_str).unwrap();
+fn execute_path(path_str: &str, options: &Options, diagnostics: &Rc<Diagnostics>) {
+    let meta = match metadata(path_str) {
+        Ok(m)    => m,
+        Err(why) => return diagnostics.report(Diagnostic::error("E0401", &format!("{}: {}", path_str, why))),
+    };
+
+    // the embedded runtime is what the default target describes
+    if options.target != Target::default() {
+        return diagnostics.report(Diagnostic::error("E0402", &format!("can only run code for {}, compile for {} with 'snail c'", Target::default(), options.target)))
+    }
 
     if meta.is_file() {
-        match file(path_str) {
-            Some(n) => execute(n),
+        match file(path_str, options, diagnostics) {
+            Some((lua, map)) => execute(path_str, lua, map, diagnostics),
             None    => (),
         }
     } else {
-        println!("{}: can't execute folder", path_str)
+        diagnostics.report(Diagnostic::error("E0402", &format!("{}: can't execute folder", path_str)))
     }
 }
 
-fn file(path: &str) -> Option<Rc<String>> {
-    let path = Path::new(path);
-    let display = path.display();
+fn source(path: &str, diagnostics: &Rc<Diagnostics>) -> Option<Rc<Source>> {
+    let mut s = String::new();
 
-    let mut file = match File::open(&path) {
-        Err(why) => panic!("failed to open {}: {}", display, why.description()),
-        Ok(file) => file,
-    };    
+    match File::open(Path::new(path)).and_then(|mut f| f.read_to_string(&mut s)) {
+        Ok(_)    => {
+            let source = Rc::new(Source::new(path, s));
+            diagnostics.set_source(Some(source.clone()));
 
-    let mut s = String::new();
+            Some(source)
+        },
+        Err(why) => {
+            diagnostics.report(Diagnostic::error("E0401", &format!("failed to read {}: {}", path, why)));
+            None
+        },
+    }
+}
+
+fn file(path: &str, options: &Options, diagnostics: &Rc<Diagnostics>) -> Option<(Rc<String>, Rc<SourceMap>)> {
+    source(path, diagnostics).and_then(|s| transpile(&mut s.text.chars(), path, options, diagnostics))
+}
+
+fn lint_path(path: &str, levels: Levels, diagnostics: &Rc<Diagnostics>) {
+    match source(path, diagnostics) {
+        Some(s) => lint(&s, levels, diagnostics),
+        None    => (),
+    }
+}
+
+fn lint(s: &Source, levels: Levels, diagnostics: &Rc<Diagnostics>) {
+    let traveler   = Traveler::new(lexer(&mut s.text.chars(), diagnostics).collect());
+    let mut parser = Parser::new(traveler);
+
+    let symtab  = Rc::new(SymTab::new_global());
+    let typetab = Rc::new(TypeTab::new_global());
+
+    add_lua_standard(&symtab, &typetab);
+
+    match parser.parse() {
+        Err(why)   => diagnostics.report(*why),
+        Ok(parsed) => Linter::new(levels, &comments(&mut s.text.chars())).lint(&parsed, &symtab, diagnostics),
+    }
+}
+
+// with no code, lists every code there is
+fn explain_code(code: Option<&String>, diagnostics: &Rc<Diagnostics>) {
+    match code {
+        Some(code) => match explain(code) {
+            Some(e) => print!("{}", e),
+            None    => diagnostics.report(Diagnostic::error("E0402", &format!("unknown code: {}", code))),
+        },
+        None => for e in CATALOG.iter() {
+            println!("{}: {}", e.code, e.title)
+        },
+    }
+}
+
+// `-A`, `-W` and `-D` followed by a rule name allow, warn or deny it
+fn lint_levels(args: &[String]) -> Result<Levels, String> {
+    let mut levels = Levels::default();
+    let mut args   = args.iter();
+
+    while let Some(flag) = args.next() {
+        let level = match flag.as_str() {
+            "-A" => Level::Allow,
+            "-W" => Level::Warn,
+            "-D" => Level::Deny,
+            f    => return Err(format!("unknown lint flag: {}", f)),
+        };
 
-    match file.read_to_string(&mut s) {
-        Err(why) => panic!("failed to read {}: {}", display,  why.description()),
-        Ok(_)    => transpile(&mut s.chars()),
+        match args.next().map(|n| (n, Rule::from_name(n))) {
+            Some((_, Some(rule))) => levels.set(rule, level),
+            Some((n, None))       => return Err(format!("unknown lint rule: {}", n)),
+            None                  => return Err(format!("missing rule after {}", flag)),
+        }
     }
+
+    Ok(levels)
 }
 
-fn transpile(s: &mut Chars) -> Option<Rc<String>> {
-    let lexer = lexer(s);
+fn transpile(s: &mut Chars, path: &str, options: &Options, diagnostics: &Rc<Diagnostics>) -> Option<(Rc<String>, Rc<SourceMap>)> {
+    let lexer = lexer(s, diagnostics);
 
     let traveler   = Traveler::new(lexer.collect());
     let mut parser = Parser::new(traveler);
@@ -107,32 +183,43 @@ fn transpile(s: &mut Chars) -> Option<Rc<String>> {
     
     add_lua_standard(&symtab, &typetab);
 
-    match parser.parse() {
-        Err(why)  => println!("error: {}", why),
-        Ok(stuff) => {                    
-            for s in stuff.iter() {
-                match s.visit(&symtab, &typetab) {
-                    Ok(()) => (),
-                    Err(e) => {
-                        println!("{}", e);
-                        break
-                    },
-                }
-            }
+    let parsed = match parser.parse() {
+        Ok(parsed) => parsed,
+        Err(why)   => {
+            diagnostics.report(*why);
+            return None
+        },
+    };
 
-            let mut output = String::new();
-            
-            for s in stuff.iter() {
-                output.push_str(&format!("{}", s))
+    let lower_sym = Rc::new(SymTab::new_global());
+
+    let stuff = match lower_program(&parsed, &lower_sym) {
+        Ok(s)  => s,
+        Err(e) => {
+            diagnostics.report(*e);
+            return None
+        },
+    };
+
+    visit_program(&stuff, &symtab, &typetab, diagnostics);
+
+    if diagnostics.has_errors() {
+        return None
+    }
+
+    match Program(&stuff).emit(options.clone(), path) {
+        Ok((lua, map)) => Some((Rc::new(lua), Rc::new(map))),
+        Err(errors)    => {
+            for e in errors {
+                diagnostics.report(e)
             }
-            
-            return Some(Rc::new(output))
+
+            None
         },
     }
-    None
 }
 
-fn write(path: &str, data: Rc<String>) {
+fn write(path: &str, data: Rc<String>, map: Rc<SourceMap>, diagnostics: &Rc<Diagnostics>) {
     let path = Path::new(path);
     println!("building: {}", path.display());
 
@@ -149,15 +236,25 @@ fn write(path: &str, data: Rc<String>) {
 
     let output_name = format!("{}/{}.lua", parent_path, split.get(0).unwrap());
 
-    let mut output_file = File::create(output_name).unwrap();
-    match output_file.write_all(data.as_bytes()) {
+    match File::create(&output_name).and_then(|mut f| f.write_all(data.as_bytes())) {
+        Ok(_)    => (),
+        Err(why) => diagnostics.report(Diagnostic::error("E0401", &format!("failed to write {}: {}", output_name, why))),
+    }
+
+    let map_name = format!("{}.map", output_name);
+    let lua_name = format!("{}.lua", split.get(0).unwrap());
+    let map_json = source_map(&map, &lua_name, path.file_name().unwrap().to_str().unwrap());
+
+    match File::create(&map_name).and_then(|mut f| f.write_all(map_json.as_bytes())) {
         Ok(_)    => (),
-        Err(why) => println!("{}", why.description())
+        Err(why) => diagnostics.report(Diagnostic::error("E0401", &format!("failed to write {}: {}", map_name, why))),
     }
 }
 
-fn execute(data: Rc<String>) {
+fn execute(path: &str, data: Rc<String>, map: Rc<SourceMap>, diagnostics: &Rc<Diagnostics>) {
     let mut lua = Lua::new();
+
+    lua.openlibs();
     
     fn print(a: String) {
         println!("{}", a)
@@ -167,34 +264,118 @@ fn execute(data: Rc<String>) {
         a.trim().to_owned()
     }
     
-    fn read() -> String {
+    fn read() -> Option<String> {
         let mut line = String::new();
         let stdin    = io::stdin();
         
-        stdin.lock().read_line(&mut line).unwrap();
-        line
+        match stdin.lock().read_line(&mut line).unwrap() {
+            0 => None,
+            _ => Some(line),
+        }
     }
 
     lua.set("print", hlua::function1(print));
     lua.set("trim",  hlua::function1(trim));
     lua.set("read",  hlua::function0(read));
-    
-    match lua.execute::<()>(&data) {
-        Ok(_)    => (),
-        Err(why) => println!("{}", format!("{}", why).to_lowercase()),
+
+    lua.set("__snail_chunk", data.as_str());
+
+    // errors are caught inside Lua, where a traceback can still be taken
+    let driver = format!(r#"
+        local chunk, why = load(__snail_chunk, "={}")
+
+        if not chunk then
+            __snail_failure, __snail_error = "E0302", why
+            return
+        end
+
+        local ok, why = xpcall(chunk, debug.traceback)
+
+        if not ok then
+            __snail_failure, __snail_error = "E0301", tostring(why)
+        end
+    "#, CHUNK);
+
+    if let Err(why) = lua.execute::<()>(&driver) {
+        return diagnostics.report(Diagnostic::error("E0301", &format!("{}", why)))
+    }
+
+    let code = match lua.get::<String, _>("__snail_failure") {
+        Some(ref c) if c == "E0302" => "E0302",
+        Some(_)                     => "E0301",
+        None                        => return,
+    };
+
+    let error = lua.get::<String, _>("__snail_error").unwrap_or_default();
+    diagnostics.report(runtime_error(code, &error, path, &map))
+}
+
+// generated code is loaded under this name, which the source map rewrites
+const CHUNK: &str = "generated.lua";
+
+// a Lua error and its traceback, with every Lua position pointed at the snail
+// code it was generated from
+fn runtime_error(code: &'static str, error: &str, path: &str, map: &SourceMap) -> Diagnostic {
+    let mut lines = error.lines();
+    let first     = lines.next().unwrap_or("");
+
+    let message = match first.find(": ") {
+        Some(i) if first.starts_with(CHUNK) => &first[i + 2 ..],
+        _                                   => first,
+    };
+
+    // Lua only knows the mangled names, people only know the snail ones
+    let mut diagnostic = Diagnostic::error(code, &demangle(&map.rewrite(message, CHUNK, path)));
+
+    if let Some(span) = map.first_span(first, CHUNK) {
+        diagnostic = diagnostic.with_span(span)
+    }
+
+    let frames: Vec<String> = lines
+        .filter(|l| l.contains(CHUNK) || l.trim_start().starts_with("(..."))
+        .map(|l| format!("    {}", demangle(&map.rewrite(l.trim(), CHUNK, path))))
+        .collect();
+
+    if frames.is_empty() {
+        diagnostic
+    } else {
+        diagnostic.with_note(&format!("stack traceback:\n{}", frames.join("\n")))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MessageFormat {
+    Human,
+    Json,
+}
+
+// colored only when a person is likely reading it
+fn emit(diagnostics: &Diagnostics, format: MessageFormat) {
+    let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+
+    for d in diagnostics.take() {
+        match format {
+            MessageFormat::Human => eprintln!("{}\n", render(&d, color)),
+            MessageFormat::Json  => eprintln!("{}", json(&d)),
+        }
     }
 }
 
 fn repl() {
-    let mut rl = rustyline::Editor::<()>::new();
+    let mut rl = rustyline::DefaultEditor::new().unwrap();
 
     loop {
         let readline = rl.readline(">");
+        let diagnostics = Rc::new(Diagnostics::new());
 
         match readline {
-            Ok(line) => match transpile(&mut line.chars()) {
-                Some(n) => execute(n),
-                None    => (),
+            Ok(line) => {
+                diagnostics.set_source(Some(Rc::new(Source::new("<repl>", line.clone()))));
+
+                match transpile(&mut line.chars(), "<repl>", &Options::default(), &diagnostics) {
+                    Some((lua, map)) => execute("<repl>", lua, map, &diagnostics),
+                    None    => (),
+                }
             },
             Err(ReadlineError::Interrupted) => {
                 println!("interrupted");
@@ -207,29 +388,646 @@ fn repl() {
             }
 
             Err(err) => {
-                println!("error: {:?}", err);
+                diagnostics.report(Diagnostic::error("E0401", &format!("{:?}", err)));
+                emit(&diagnostics, MessageFormat::Human);
                 break
             }
         }
+
+        emit(&diagnostics, MessageFormat::Human)
     }
 }
 
 fn main() {
-    match env::args().nth(1) {
-        Some(a) => match a.as_str() {
-            "c" => {
-                write_path(
-                    &match env::args().nth(2) {
-                        Some(n) => n,
-                        None    => {
-                            println!("missing supplied path");
-                            return
-                        }
-                    }
-                )
+    let diagnostics = Rc::new(Diagnostics::new());
+
+    let mut format  = MessageFormat::Human;
+    let mut options = Options::default();
+    let mut args    = Vec::new();
+
+    for a in env::args().skip(1) {
+        match a.as_str() {
+            "--message-format=human" => format = MessageFormat::Human,
+            "--message-format=json"  => format = MessageFormat::Json,
+            "--source-comments"      => options.comments = true,
+            t if t.starts_with("--target=") => match Target::from_name(&t["--target=".len() ..]) {
+                Some(target) => options.target = target,
+                None         => {
+                    let names: Vec<&str> = Target::all().iter().map(|t| t.name()).collect();
+                    diagnostics.report(Diagnostic::error("E0402", &format!("unknown target: {}, expected one of {}", &t["--target=".len() ..], names.join(", "))))
+                },
             },
-            _ => execute_path(&a),
-        },
-        None => println!("repl is wip"),
+            f if f.starts_with("--message-format=") => {
+                diagnostics.report(Diagnostic::error("E0402", &format!("unknown message format: {}, expected 'human' or 'json'", &f["--message-format=".len() ..])))
+            },
+            _ => args.push(a),
+        }
+    }
+
+    if !diagnostics.has_errors() {
+        match args.first() {
+            Some(a) => match a.as_str() {
+                "c" => match args.get(1) {
+                    Some(n) => write_path(n, &options, &diagnostics),
+                    None    => diagnostics.report(Diagnostic::error("E0402", "missing supplied path")),
+                },
+                "lint" => match (args.get(1), lint_levels(&args[::std::cmp::min(2, args.len()) ..])) {
+                    (Some(path), Ok(levels)) => lint_path(path, levels, &diagnostics),
+                    (None, _)                => diagnostics.report(Diagnostic::error("E0402", "missing supplied path")),
+                    (_, Err(why))            => diagnostics.report(Diagnostic::error("E0402", &why)),
+                },
+                "explain" => explain_code(args.get(1), &diagnostics),
+                _         => execute_path(a, &options, &diagnostics),
+            },
+            None => println!("repl is wip"),
+        }
+    }
+
+    let failed = diagnostics.has_errors();
+
+    emit(&diagnostics, format);
+
+    if failed {
+        ::std::process::exit(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every code an example reports, linting it for lint codes and running it
+    // for runtime ones
+    fn codes(example: &str, e: &Explanation) -> Vec<&'static str> {
+        let diagnostics = Rc::new(Diagnostics::new());
+        let code        = e.code;
+
+        let options = Options {
+            target: e.target.map_or(Target::default(), |t| Target::from_name(t).unwrap()),
+            ..Options::default()
+        };
+
+        if code.starts_with('W') {
+            lint(&Source::new("example.snail", example.to_owned()), Levels::default(), &diagnostics)
+        } else if let Some((lua, map)) = transpile(&mut example.chars(), "example.snail", &options, &diagnostics) {
+            if code.starts_with("E03") {
+                execute("example.snail", lua, map, &diagnostics)
+            }
+        }
+
+        diagnostics.take().iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn catalog_examples_behave_as_documented() {
+        for e in CATALOG.iter() {
+            if let Some(bad) = e.bad {
+                let found = codes(bad, e);
+                assert!(found.contains(&e.code), "{}: erroneous example reported {:?}", e.code, found)
+            }
+
+            if let Some(good) = e.good {
+                let found = codes(good, e);
+                assert!(found.is_empty(), "{}: corrected example reported {:?}", e.code, found)
+            }
+        }
+    }
+
+    // everything compiling and running a program reports, quoting test.snail
+    fn reported(program: &str) -> Vec<Diagnostic> {
+        let diagnostics = Rc::new(Diagnostics::new());
+
+        diagnostics.set_source(Some(Rc::new(Source::new("test.snail", program.to_owned()))));
+
+        if let Some((lua, map)) = transpile(&mut program.chars(), "test.snail", &Options::default(), &diagnostics) {
+            execute("test.snail", lua, map, &diagnostics)
+        }
+
+        diagnostics.take()
+    }
+
+    fn diagnosed(program: &str) -> Vec<&'static str> {
+        reported(program).iter().map(|d| d.code).collect()
+    }
+
+    // the uncolored text a program's diagnostics are printed as
+    fn rendered(program: &str) -> Vec<String> {
+        reported(program).iter().map(|d| render(d, false)).collect()
+    }
+
+    // runs a program that must compile cleanly and reads one of its exports
+    // the way Lua's tostring shows it
+    fn exported(program: &str, name: &str) -> String {
+        let diagnostics = Rc::new(Diagnostics::new());
+        let transpiled  = transpile(&mut program.chars(), "test.snail", &Options::default(), &diagnostics);
+
+        let found: Vec<String> = diagnostics.take().iter().map(|d| d.message.clone()).collect();
+        assert!(found.is_empty(), "{:?}", found);
+
+        let (code, _) = transpiled.unwrap();
+        let mut lua   = Lua::new();
+
+        lua.openlibs();
+        lua.set("__snail_chunk", code.as_str());
+
+        match lua.execute::<String>(&format!(r#"return tostring(load(__snail_chunk)()["{}"])"#, name)) {
+            Ok(value) => value,
+            Err(why)  => panic!("{}: {:?}\n{}", name, why, code),
+        }
+    }
+
+    // loops are recursion, so every iteration has to be a tail call or the
+    // stack overflows long before a million
+    #[test]
+    fn while_loops_run_in_constant_stack() {
+        let program = "
+when := {
+  |true body| body!
+  |false _|
+}
+
+while := {
+  |condition body| when condition!, {
+    body!
+    while condition, body
+  }
+}
+
+mut i := 0
+
+while { i < 1000000 }, {
+  i = i + 1
+}
+";
+
+        assert_eq!(exported(program, "i"), "1000000")
+    }
+
+    // the last expression of a block is returned even after local definitions
+    #[test]
+    fn blocks_return_their_last_expression() {
+        let program = "
+g := { |x| {
+  y := x ++ \"s\"
+  x - 1
+} }
+
+h := { |x| {
+  y := x + 1
+  y * 2
+} }
+
+a := (g 3)!
+b := (h 3)!
+";
+
+        assert_eq!(exported(program, "a"), "2");
+        assert_eq!(exported(program, "b"), "8")
+    }
+
+    #[test]
+    fn named_arguments_fill_defaults_at_compile_time() {
+        let program = "
+greet := {
+  |name greeting = \"hello\"| greeting ++ \", \" ++ name
+}
+
+a := greet \"bob\"
+b := greet \"bob\", greeting: \"hi\"
+c := greet greeting: \"hey\", name: \"al\"
+";
+
+        assert_eq!(exported(program, "a"), "hello, bob");
+        assert_eq!(exported(program, "b"), "hi, bob");
+        assert_eq!(exported(program, "c"), "hey, al")
+    }
+
+    #[test]
+    fn named_arguments_need_an_arm_that_takes_them() {
+        let greet = "
+greet := {
+  |name greeting = \"hello\"| greeting ++ \", \" ++ name
+}
+";
+
+        assert_eq!(diagnosed(&format!("{}a := greet \"bob\", tone: \"hi\"", greet)), vec!["E0210"]);
+        assert_eq!(diagnosed(&format!("{}f := greet\na := f name: \"bob\"", greet)), vec!["E0210"]);
+        assert_eq!(diagnosed(&format!("{}a := greet name: \"bob\", name: \"al\"", greet)), vec!["E0209"])
+    }
+
+    // arms that fill the same defaults agree, wherever each default was written
+    #[test]
+    fn named_arguments_agree_across_arms_with_equal_defaults() {
+        let program = "
+scale := {
+  |n: int by = 2| n * by
+  |n: float by = 2| n * by
+}
+
+a := scale n: 3
+b := scale n: 1.5
+";
+
+        assert_eq!(exported(program, "a"), "6");
+        assert_eq!(exported(program, "b"), "3")
+    }
+
+    // a union only says what a value may be, the arm is picked by what it is
+    #[test]
+    fn typed_arms_dispatch_on_the_runtime_type() {
+        let program = "
+describe := {
+  |n: int| \"int\"
+  |n: float| \"float\"
+  |s: str| \"str\"
+  |b: bool| \"bool\"
+}
+
+x: int | str = \"a\"
+y: int | str = 1
+
+a := describe 3
+b := describe 2.5
+c := describe x
+d := describe y
+e := describe true
+";
+
+        for &(name, arm) in [("a", "int"), ("b", "float"), ("c", "str"), ("d", "int"), ("e", "bool")].iter() {
+            assert_eq!(exported(program, name), arm, "{}", name)
+        }
+    }
+
+    // the type checking settles on for a top-level definition
+    fn type_of(program: &str, name: &str) -> String {
+        let diagnostics = Rc::new(Diagnostics::new());
+        let mut parser  = Parser::new(Traveler::new(lexer(&mut program.chars(), &diagnostics).collect()));
+
+        let symtab  = Rc::new(SymTab::new_global());
+        let typetab = Rc::new(TypeTab::new_global());
+
+        add_lua_standard(&symtab, &typetab);
+
+        let lowered = lower_program(&parser.parse().unwrap(), &Rc::new(SymTab::new_global())).unwrap();
+        visit_program(&lowered, &symtab, &typetab, &diagnostics);
+
+        let found: Vec<String> = diagnostics.take().iter().map(|d| d.message.clone()).collect();
+        assert!(found.is_empty(), "{:?}", found);
+
+        let (i, env_index) = symtab.get_name(name).unwrap();
+        format!("{:?}", typetab.resolve(&typetab.get_type(i, env_index).unwrap()))
+    }
+
+    #[test]
+    fn inference_types_definitions_from_their_use() {
+        let program = "
+inc := { |x| x + 1 }
+tidy := { |s| trim s }
+apply := { |f x| f x }
+n := inc 2
+m := apply tidy, \" a \"
+";
+
+        assert_eq!(type_of(program, "inc"), "Block((Num) -> Num)");
+        assert_eq!(type_of(program, "tidy"), "Block((Str) -> Str)");
+        assert_eq!(type_of(program, "n"), "Num");
+        assert_eq!(type_of(program, "m"), "Str")
+    }
+
+    // top-level definitions are generalized, each use gets its own instance
+    #[test]
+    fn definitions_are_polymorphic() {
+        let program = "
+id := { |x| x }
+a := id 1
+b := id \"s\"
+c := id true
+";
+
+        let id    = type_of(program, "id");
+        let param = &id["Block((".len() .. id.find(')').unwrap()];
+
+        assert_eq!(id, format!("Block(({}) -> {})", param, param));
+        assert_eq!(type_of(program, "a"), "Int");
+        assert_eq!(type_of(program, "b"), "Str");
+        assert_eq!(type_of(program, "c"), "Bool");
+
+        assert_eq!(exported(program, "b"), "s")
+    }
+
+    // a literal pattern has the type the same literal has as an expression
+    #[test]
+    fn literal_patterns_are_typed_like_literals() {
+        let program = "
+apply: (int -> int), int -> int = {
+  |f x| f x
+}
+
+zero := { |0| 1 }
+a := apply zero, 0
+";
+
+        assert_eq!(type_of(program, "zero"), "Block((Int) -> Int)");
+        assert_eq!(exported(program, "a"), "1")
+    }
+
+    // host functions can't take nil even where they take anything else
+    #[test]
+    fn possibly_nil_arguments_must_be_narrowed() {
+        assert_eq!(diagnosed("print read!\n"), vec!["E0203"]);
+        assert_eq!(diagnosed("x := read!\nprint tostring x\n"), vec!["E0203"]);
+        assert_eq!(diagnosed("x := read! ? \"\"\nprint x\n"), Vec::<&str>::new())
+    }
+
+    #[test]
+    fn clashes_label_where_a_type_was_inferred() {
+        assert_eq!(rendered("f := { |x| x + 1 }\nf \"a\"\n"), vec![
+"error[E0202]: f: argument 1 expected 'Num', got 'Str'
+ --> test.snail:2:3
+  |
+1 | f := { |x| x + 1 }
+  |            ----- 'Num' inferred here
+2 | f \"a\"
+  |   ^^^"
+        ])
+    }
+
+    #[test]
+    fn operator_errors_label_both_operands() {
+        assert_eq!(rendered("x := \"a\" ++ 1 - true\n"), vec![
+"error[E0204]: failed to subtract: Num and Bool
+ --> test.snail:1:13
+  |
+1 | x := \"a\" ++ 1 - true
+  |             ^^^^^^^^
+  |             - 'Int' here
+  |                 ---- 'Bool' here"
+        ])
+    }
+
+    // every definition that doesn't check is reported once, and the rest still are
+    #[test]
+    fn failed_definitions_are_reported_once() {
+        let program = "
+f := { |x| x + 1 }
+g := { |y| (f y) ++ \"a\" }
+h := { |z| z < \"s\" }
+w := (f 1) - true
+";
+
+        let lines: Vec<usize> = reported(program).iter().map(|d| d.span.unwrap().start.line).collect();
+        assert_eq!(lines, vec![3, 4, 5])
+    }
+
+    // tokens are underlined as they were written, not as they were read
+    #[test]
+    fn underlines_cover_the_source_of_a_token() {
+        assert_eq!(rendered("f := { |s: str| s }\nf 1.50\n"), vec![
+"error[E0202]: f: argument 1 expected 'Str', got 'Float'
+ --> test.snail:2:3
+  |
+2 | f 1.50
+  |   ^^^^"
+        ]);
+        assert_eq!(rendered("f := { |n: int| n }\nf r\"a\\\\b\"\n"), vec![
+"error[E0202]: f: argument 1 expected 'Int', got 'Str'
+ --> test.snail:2:3
+  |
+2 | f r\"a\\\\b\"
+  |   ^^^^^^^"
+        ])
+    }
+
+    // the Lua a program that must compile cleanly compiles to
+    fn compiled(program: &str, options: &Options) -> String {
+        let diagnostics = Rc::new(Diagnostics::new());
+        let transpiled  = transpile(&mut program.chars(), "test.snail", options, &diagnostics);
+
+        let found: Vec<String> = diagnostics.take().iter().map(|d| d.message.clone()).collect();
+        assert!(found.is_empty(), "{:?}", found);
+
+        transpiled.unwrap().0.to_string()
+    }
+
+    const NESTED: &str = "twice := {
+  |n| {
+    m := n * 2
+    m
+  }
+}
+
+four := twice 2
+";
+
+    #[test]
+    fn nested_blocks_are_indented() {
+        assert_eq!(compiled(NESTED, &Options::default()),
+"local twice, four
+
+twice = function(...)
+  local __args = {...}
+  if 1 == select(\"#\", ...) then
+    local n = __args[1]
+    return function(...)
+      local __args = {...}
+      local m = (n * 2)
+      return m
+    end
+  end
+end
+
+four = twice(2)
+
+return {twice = twice, four = four}
+")
+    }
+
+    // comments sit at the statement's own depth and only where the line changes
+    #[test]
+    fn source_comments_precede_statements() {
+        let options = Options { comments: true, ..Options::default() };
+
+        assert_eq!(compiled(NESTED, &options),
+"local twice, four
+
+-- snail: test.snail:1
+twice = function(...)
+  local __args = {...}
+  -- snail: test.snail:2
+  if 1 == select(\"#\", ...) then
+    local n = __args[1]
+    return function(...)
+      local __args = {...}
+      -- snail: test.snail:3
+      local m = (n * 2)
+      -- snail: test.snail:4
+      return m
+    end
+  end
+end
+
+-- snail: test.snail:8
+four = twice(2)
+
+return {twice = twice, four = four}
+")
+    }
+
+    // arm patterns are emitted like any other literal, rejections included
+    #[test]
+    fn literal_patterns_are_checked_against_the_target() {
+        let program = "big := {
+  |9007199254740993| \"big\"
+  |n| \"small\"
+}
+
+x := big 1
+";
+        let options     = Options { target: Target::Lua51, ..Options::default() };
+        let diagnostics = Rc::new(Diagnostics::new());
+
+        assert!(transpile(&mut program.chars(), "test.snail", &options, &diagnostics).is_none());
+
+        let found: Vec<(&str, usize)> = diagnostics.take().iter().map(|d| (d.code, d.span.unwrap().start.col)).collect();
+        assert_eq!(found, vec![("E0502", 3)])
+    }
+
+    // the schema tools read with --message-format=json, one line per diagnostic
+    #[test]
+    fn json_diagnostics_keep_their_schema() {
+        let lines = |program: &str| -> Vec<String> { reported(program).iter().map(json).collect() };
+
+        assert_eq!(lines("x := 1\nprnt x\n"), vec![
+            r#"{"version":1,"code":"E0101","severity":"error","message":"prnt: undeclared, did you mean 'print'?","file":"test.snail","span":{"byte_start":7,"byte_end":11,"line_start":2,"column_start":1,"line_end":2,"column_end":5},"labels":[],"notes":[],"suggestions":[{"span":{"byte_start":7,"byte_end":11,"line_start":2,"column_start":1,"line_end":2,"column_end":5},"replacement":"print","message":"did you mean 'print'?"}]}"#
+        ]);
+        assert_eq!(lines("x := \"a\" ++ 1 - true\n"), vec![
+            r#"{"version":1,"code":"E0204","severity":"error","message":"failed to subtract: Num and Bool","file":"test.snail","span":{"byte_start":12,"byte_end":20,"line_start":1,"column_start":13,"line_end":1,"column_end":21},"labels":[{"span":{"byte_start":12,"byte_end":13,"line_start":1,"column_start":13,"line_end":1,"column_end":14},"message":"'Int' here"},{"span":{"byte_start":16,"byte_end":20,"line_start":1,"column_start":17,"line_end":1,"column_end":21},"message":"'Bool' here"}],"notes":[],"suggestions":[]}"#
+        ]);
+        assert_eq!(lines("x := 1\nx = 2\n"), vec![
+            r#"{"version":1,"code":"E0212","severity":"error","message":"x: can't reassign immutable binding","file":"test.snail","span":{"byte_start":7,"byte_end":8,"line_start":2,"column_start":1,"line_end":2,"column_end":2},"labels":[],"notes":["declare it with 'mut' to allow reassignment"],"suggestions":[]}"#
+        ])
+    }
+
+    // what `snail lint` reports for a program given the level flags, and
+    // whether that fails the command
+    fn linted(program: &str, flags: &[&str]) -> (Vec<(&'static str, Severity, usize)>, bool) {
+        let diagnostics = Rc::new(Diagnostics::new());
+        let flags: Vec<String> = flags.iter().map(|f| f.to_string()).collect();
+
+        lint(&Source::new("test.snail", program.to_owned()), lint_levels(&flags).unwrap(), &diagnostics);
+
+        let failed = diagnostics.has_errors();
+        let lints  = diagnostics.take().iter().map(|d| (d.code, d.severity, d.span.unwrap().start.line)).collect();
+
+        (lints, failed)
+    }
+
+    #[test]
+    fn lint_levels_warn_allow_or_deny() {
+        let program = "
+f := {
+  |a b| a
+}
+
+g := {
+  x := 1
+  2
+}
+";
+
+        assert_eq!(linted(program, &[]), (vec![("W0002", Severity::Warning, 3), ("W0001", Severity::Warning, 7)], false));
+        assert_eq!(linted(program, &["-A", "unused_parameter"]), (vec![("W0001", Severity::Warning, 7)], false));
+        assert_eq!(linted(program, &["-D", "unused_definition", "-A", "unused_parameter"]), (vec![("W0001", Severity::Error, 7)], true));
+        assert_eq!(linted(program, &["-D", "unused_parameter", "-W", "unused_parameter"]), (vec![("W0002", Severity::Warning, 3), ("W0001", Severity::Warning, 7)], false));
+
+        assert!(lint_levels(&["-X".to_owned(), "shadowing".to_owned()]).is_err());
+        assert!(lint_levels(&["-D".to_owned(), "nonsense".to_owned()]).is_err());
+        assert!(lint_levels(&["-D".to_owned()]).is_err())
+    }
+
+    // an allow comment covers its own line and the next, and only the rules it names
+    #[test]
+    fn allow_comments_silence_rules_nearby() {
+        let program = "
+f := {
+  |a b| a   # allow(unused_parameter)
+}
+
+# allow(unused_parameter, shadowing)
+g := { |c| 1 }
+
+h := {
+
+  # allow(unused_parameter)
+
+  |d| 1
+}
+
+k := { |e| 1 }   # allow(unused_definition)
+";
+
+        assert_eq!(linted(program, &["-D", "unused_parameter"]), (vec![("W0002", Severity::Error, 13), ("W0002", Severity::Error, 16)], true))
+    }
+
+    #[test]
+    fn mangled_names_round_trip() {
+        for name in ["if", "end'", "a__b", "x'", "empty?", "select", "type", "naïve", "λ"].iter() {
+            let lua = lua_identifier(name);
+
+            assert!(lua.starts_with("__"), "{} was kept as {}", name, lua);
+            assert_eq!(snail_identifier(&lua), Some(name.to_string()), "{} mangled to {}", name, lua)
+        }
+
+        // `_if` was how keywords used to be escaped, it's an ordinary name now
+        for name in ["x", "_if", "fib", "print"].iter() {
+            assert_eq!(lua_identifier(name), *name);
+            assert_eq!(snail_identifier(name), None)
+        }
+
+        assert_ne!(lua_identifier("if"), lua_identifier("_if"));
+
+        // generated helpers look mangled, but no snail name mangles to them
+        assert_eq!(snail_identifier("__args"), None);
+        assert_eq!(demangle("attempt to call a nil value (upvalue '__empty_q')"), "attempt to call a nil value (upvalue 'empty?')")
+    }
+
+    // overloads dispatch on the global `select`, which a definition can't hide
+    #[test]
+    fn definitions_named_like_lua_globals_keep_dispatch_working() {
+        let program = "
+select := { |x y| x }
+type := { |x| x }
+f := { |n| n }
+
+print tostring f 3
+print tostring (type 4)
+";
+
+        let diagnostics = Rc::new(Diagnostics::new());
+
+        if let Some((lua, map)) = transpile(&mut program.chars(), "globals.snail", &Options::default(), &diagnostics) {
+            execute("globals.snail", lua, map, &diagnostics)
+        }
+
+        let found: Vec<String> = diagnostics.take().iter().map(|d| d.message.clone()).collect();
+        assert!(found.is_empty(), "{:?}", found)
+    }
+
+    // i/o and usage errors don't come from a program, and a load failure is a
+    // compiler bug, every other code shows what causes it
+    #[test]
+    fn catalog_examples_cover_program_errors() {
+        for e in CATALOG.iter() {
+            if !["E0302", "E0401", "E0402"].contains(&e.code) {
+                assert!(e.bad.is_some() && e.good.is_some(), "{} has no examples", e.code)
+            }
+        }
+    }
+
+    #[test]
+    fn catalog_codes_are_unique() {
+        for (i, e) in CATALOG.iter().enumerate() {
+            assert!(CATALOG[i + 1 ..].iter().all(|o| o.code != e.code), "{} is listed twice", e.code)
+        }
     }
 }
@@ -0,0 +1,293 @@
+use std::fmt;
+
+// the long form of a diagnostic code, shown by `snail explain`
+pub struct Explanation {
+    pub code:   &'static str,
+    pub title:  &'static str,
+    pub text:   &'static str,
+    pub bad:    Option<&'static str>,
+    pub good:   Option<&'static str>,
+    // the --target both examples are compiled for, the default when None
+    pub target: Option<&'static str>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}\n\n{}\n", self.code, self.title, self.text)?;
+
+        if let (Some(target), true) = (self.target, self.bad.is_some() || self.good.is_some()) {
+            write!(f, "\nthe examples are compiled with --target={}\n", target)?;
+        }
+
+        let examples = [("erroneous example:", self.bad), ("corrected:", self.good)];
+
+        for &(heading, example) in examples.iter() {
+            if let Some(example) = example {
+                write!(f, "\n{}\n\n", heading)?;
+
+                for line in example.lines() {
+                    writeln!(f, "    {}", line)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    CATALOG.iter().find(|e| e.code.eq_ignore_ascii_case(code))
+}
+
+// every example is compiled by a test, the erroneous one has to report its code
+// and the corrected one has to report no error at all
+pub const CATALOG: &[Explanation] = &[
+    Explanation {
+        code:   "E0001",
+        title:  "syntax error",
+        text:   "The parser found a token where it can't go, like an unclosed parenthesis or a\nmissing '->' in a type. The message says what was expected and what was found.",
+        bad:    Some("x := (1 + 2\n"),
+        good:   Some("x := (1 + 2)\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0002",
+        title:  "integer literal out of range",
+        text:   "Integer literals are kept exact, so they have to fit in a 64 bit integer. Write\nthe number with a decimal point to make it a float instead.",
+        bad:    Some("big := 99999999999999999999\n"),
+        good:   Some("big := 99999999999999999999.0\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0003",
+        title:  "invalid token",
+        text:   "A character that doesn't start any token was found outside a string or a\ncomment. Lexing carries on after it, so every stray character is reported.",
+        bad:    Some("x := 1 $ 2\n"),
+        good:   Some("x := 1 + 2\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0004",
+        title:  "malformed literal",
+        text:   "A number has a misplaced decimal point, or a string uses an escape that doesn't\nexist or isn't closed before the end of its line. Known escapes are \\n, \\t,\n\\r, \\\", \\' and \\\\.",
+        bad:    Some("path := \"C:\\temp\\q\"\n"),
+        good:   Some("path := \"C:\\\\temp\\\\q\"\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0101",
+        title:  "undeclared name",
+        text:   "A name is used or assigned that no visible scope declares. Close names that\nare in scope are suggested.",
+        bad:    Some("prnt \"hi\"\n"),
+        good:   Some("print \"hi\"\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0102",
+        title:  "used before its definition",
+        text:   "Top-level names are declared up front so blocks can refer to later\ndefinitions, but code running outside a block would read the value before it\nexists. Move the definition up, or the use into a block.",
+        bad:    Some("a := b + 1\nb := 2\n"),
+        good:   Some("b := 2\na := b + 1\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0103",
+        title:  "undeclared variant",
+        text:   "An arm matches on a variant that no data type declares.",
+        bad:    Some("type Shape = Circle num | Rect num num\n\narea := {\n  |Circle r| 3 * r * r\n  |Square s| s * s\n}\n"),
+        good:   Some("type Shape = Circle num | Rect num num | Square num\n\narea := {\n  |Circle r| 3 * r * r\n  |Rect w h| w * h\n  |Square s| s * s\n}\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0201",
+        title:  "mismatched types",
+        text:   "A value doesn't fit the type it's given, declared or assigned to.",
+        bad:    Some("name: str = 10\n"),
+        good:   Some("name: str = \"ten\"\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0202",
+        title:  "call doesn't fit the signature",
+        text:   "A call passes the wrong number of arguments, or an argument whose type the\nparameter doesn't accept.",
+        bad:    Some("double := { |n: num| n * 2 }\n\ndouble \"two\"\n"),
+        good:   Some("double := { |n: num| n * 2 }\n\ndouble 2\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0203",
+        title:  "possibly nil",
+        text:   "An optional value is operated on, or passed as an argument, before it's known\nnot to be nil. Narrow it with '?' and a default, or with an arm that has a\n'|nil|' pattern.",
+        bad:    Some("line := read!\nshout := line ++ \"!\"\n"),
+        good:   Some("line := read! ? \"\"\nshout := line ++ \"!\"\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0204",
+        title:  "invalid operands",
+        text:   "An operator is applied to types it doesn't work on, like subtracting a bool\nor using a bitwise operator on floats.",
+        bad:    Some("x := 1 - true\n"),
+        good:   Some("x := 1 - 1\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0205",
+        title:  "not callable",
+        text:   "Something that isn't a block is called, or something that isn't an arm is\nused where one is expected.",
+        bad:    Some("x := 1\ny := x 2\n"),
+        good:   Some("x := { |n| n }\ny := x 2\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0206",
+        title:  "no such member",
+        text:   "A field is read from a record that doesn't have it, or a method is called on a\nmember that doesn't take the record as its first parameter.",
+        bad:    Some("point := [x: 1, y: 2]\nz := point.z\n"),
+        good:   Some("point := [x: 1, y: 2]\nz := point.y\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0207",
+        title:  "non-exhaustive arms",
+        text:   "Arms matching on the variants of a data type have to cover all of them. The\nmessage lists the ones that are missing.",
+        bad:    Some("type Shape = Circle num | Rect num num\n\narea := {\n  |Circle r| 3 * r * r\n}\n"),
+        good:   Some("type Shape = Circle num | Rect num num\n\narea := {\n  |Circle r| 3 * r * r\n  |Rect w h| w * h\n}\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0208",
+        title:  "missing return value",
+        text:   "A block without arms returns its last expression, so it can't end in a\ndefinition.",
+        bad:    Some("f := {\n  x := 1\n}\n"),
+        good:   Some("f := {\n  x := 1\n  x\n}\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0209",
+        title:  "given more than once",
+        text:   "A record lists the same field twice, or a call gives the same argument both by\nposition and by name.",
+        bad:    Some("point := [x: 1, x: 2]\n"),
+        good:   Some("point := [x: 1, y: 2]\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0210",
+        title:  "named arguments can't be resolved",
+        text:   "Named arguments are matched to parameters at compile time, so the callee must\nbe a known definition with exactly one arm taking those names.",
+        bad:    Some("open := {\n  |path mode = \"r\"| path ++ mode\n}\n\nopen \"log.txt\", flags: \"w\"\n"),
+        good:   Some("open := {\n  |path mode = \"r\"| path ++ mode\n}\n\nopen \"log.txt\", mode: \"w\"\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0211",
+        title:  "no arm accepts a case",
+        text:   "A union is passed to typed arms, and one of its members isn't accepted by any\nof them. Add an arm for it.",
+        bad:    Some("describe := {\n  |n: num| \"number\"\n  |b: bool| \"bool\"\n}\n\nx: num | str = 1\ndescribe x\n"),
+        good:   Some("describe := {\n  |n: num| \"number\"\n  |s: str| \"string\"\n}\n\nx: num | str = 1\ndescribe x\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0212",
+        title:  "immutable reassignment",
+        text:   "Bindings can't be reassigned unless they're declared with 'mut'.",
+        bad:    Some("limit := 10\nlimit = 20\n"),
+        good:   Some("mut limit := 10\nlimit = 20\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0213",
+        title:  "invalid assignment target",
+        text:   "Only names and record fields can be assigned to.",
+        bad:    Some("mut x := 1\n(x + 1) = 2\n"),
+        good:   Some("mut x := 1\nx = 2\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0214",
+        title:  "wrong number of variant fields",
+        text:   "A variant pattern has to name every field of the variant, use '_' for the\nones that aren't needed.",
+        bad:    Some("type Shape = Circle num | Rect num num\n\narea := {\n  |Circle r| 3 * r * r\n  |Rect w| w * w\n}\n"),
+        good:   Some("type Shape = Circle num | Rect num num\n\narea := {\n  |Circle r| 3 * r * r\n  |Rect w h| w * h\n}\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0301",
+        title:  "runtime error",
+        text:   "The program compiled but Lua raised an error while running it, like running\nout of stack in deep recursion.",
+        bad:    Some("deep := { |n: num| 1 + deep n + 1 }\nprint deep 0\n"),
+        good:   Some("count := {\n  |0| 0\n  |n: num| 1 + count n - 1\n}\n\nprint count 10\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "E0302",
+        title:  "generated Lua failed to load",
+        text:   "The generated Lua isn't valid. Every snail name is mangled into a valid Lua\nname, so this is a bug in snail, please report it with the program.",
+        bad:    None,
+        good:   None,
+        target: None,
+    },
+    Explanation {
+        code:   "E0401",
+        title:  "i/o error",
+        text:   "A file or folder given to snail couldn't be read, or the compiled Lua couldn't\nbe written.\n\n    snail missing.snail",
+        bad:    None,
+        good:   None,
+        target: None,
+    },
+    Explanation {
+        code:   "E0402",
+        title:  "command line usage",
+        text:   "The command line is missing a path, names an unknown lint rule or flag, or\nasks to run a folder.\n\n    snail lint\n    snail lint file.snail -D unknown_rule",
+        bad:    None,
+        good:   None,
+        target: None,
+    },
+    Explanation {
+        code:   "E0501",
+        title:  "not available on the target",
+        text:   "The construct can't be expressed on the Lua version being compiled for. The\nbitwise operators need Lua 5.2's bit32 library, LuaJIT's bit library or Lua\n5.3's operators, so Lua 5.1 can't have them. Rewrite the code without the\nconstruct, or compile for a target that has it.",
+        bad:    Some("flags := 300\nmask := flags & 255\n"),
+        good:   Some("flags := 300\nmask := flags % 256\n"),
+        target: Some("5.1"),
+    },
+    Explanation {
+        code:   "E0502",
+        title:  "integer literal loses precision on the target",
+        text:   "Before Lua 5.3 every number is a double, which holds integers exactly only up\nto 2^53. Compile for 5.3 or newer to keep larger integers exact, or write the\nliteral as a float.",
+        bad:    Some("id: int = 9007199254740993\n"),
+        good:   Some("id: int = 9007199254740992\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "W0001",
+        title:  "unused definition",
+        text:   "A local definition is never read. Reported by 'snail lint' under the\n'unused_definition' rule. Names starting with '_' are never reported.",
+        bad:    Some("area := {\n  unused := 4\n  |n| n * n\n}\n"),
+        good:   Some("area := {\n  |n| n * n\n}\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "W0002",
+        title:  "unused parameter",
+        text:   "An arm parameter is never read. Reported by 'snail lint' under the\n'unused_parameter' rule, name it '_' if that's intended.",
+        bad:    Some("first := { |a b| a }\n"),
+        good:   Some("first := { |a _| a }\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "W0003",
+        title:  "shadowing",
+        text:   "A definition or parameter hides a name from an outer scope. Reported by\n'snail lint' under the 'shadowing' rule.",
+        bad:    Some("n := 3\ndouble := { |n| n * 2 }\n"),
+        good:   Some("n := 3\ndouble := { |m| m * 2 }\n"),
+        target: None,
+    },
+    Explanation {
+        code:   "W0004",
+        title:  "host shadowing",
+        text:   "A definition hides a host global like 'print'. Reported by 'snail lint' under\nthe 'host_shadowing' rule.",
+        bad:    Some("print := { |x| x }\n"),
+        good:   Some("show := { |x| x }\n"),
+        target: None,
+    },
+];
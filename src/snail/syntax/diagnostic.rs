@@ -0,0 +1,191 @@
+use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use super::lexer::{Span, TokenPosition};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Error   => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// a file being compiled, kept around so diagnostics can quote it
+#[derive(Debug)]
+pub struct Source {
+    pub path: String,
+    pub text: String,
+}
+
+impl Source {
+    pub fn new(path: &str, text: String) -> Source {
+        Source {
+            path: path.to_owned(),
+            text,
+        }
+    }
+
+    // lines are counted from 1, like token positions
+    pub fn line(&self, line: usize) -> Option<&str> {
+        self.text.lines().nth(line.wrapping_sub(1))
+    }
+
+    // the byte a position points at, clamped to the end of its line
+    pub fn offset(&self, position: TokenPosition) -> usize {
+        let mut offset = 0;
+
+        for (n, line) in self.text.split_inclusive('\n').enumerate() {
+            if n + 1 == position.line {
+                return offset + line.char_indices().nth(position.col).map_or(line.trim_end_matches('\n').len(), |(i, _)| i)
+            }
+
+            offset += line.len()
+        }
+
+        offset
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span:    Span,
+    pub message: String,
+}
+
+// a fix a tool can apply, replacing what the span covers
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub span:        Span,
+    pub replacement: String,
+    pub message:     String,
+}
+
+// everything the compiler and runtime have to say about a program, with a stable
+// code so tools and `snail explain` can tell them apart
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity:    Severity,
+    pub code:        &'static str,
+    pub message:     String,
+    pub span:        Option<Span>,
+    pub labels:      Vec<Label>,
+    pub notes:       Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+    pub source:      Option<Rc<Source>>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: &str) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code,
+            message: message.to_owned(),
+            span:        None,
+            labels:      Vec::new(),
+            notes:       Vec::new(),
+            suggestions: Vec::new(),
+            source:      None,
+        }
+    }
+
+    pub fn error(code: &'static str, message: &str) -> Diagnostic {
+        Diagnostic::new(Severity::Error, code, message)
+    }
+
+    pub fn with_span(mut self, span: Span) -> Diagnostic {
+        self.span = Some(span);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Diagnostic {
+        self.labels.push(Label { span, message: message.to_owned() });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_owned());
+        self
+    }
+
+    pub fn with_suggestion(mut self, span: Span, replacement: &str, message: &str) -> Diagnostic {
+        self.suggestions.push(Suggestion { span, replacement: replacement.to_owned(), message: message.to_owned() });
+        self
+    }
+
+    // places a diagnostic raised without a position at the given node
+    pub fn at(self, span: Span) -> Diagnostic {
+        match self.span {
+            Some(_) => self,
+            None    => self.with_span(span),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}]: ", self.severity, self.code)?;
+
+        if let Some(span) = self.span {
+            write!(f, "{}: ", span)?
+        }
+
+        write!(f, "{}", self.message)?;
+
+        for l in self.labels.iter() {
+            write!(f, "\n  {}: {}", l.span, l.message)?
+        }
+
+        for n in self.notes.iter() {
+            write!(f, "\n  note: {}", n)?
+        }
+
+        Ok(())
+    }
+}
+
+// the sink every stage reports into
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    items:  RefCell<Vec<Diagnostic>>,
+    source: RefCell<Option<Rc<Source>>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics::default()
+    }
+
+    // the file everything reported from now on is about
+    pub fn set_source(&self, source: Option<Rc<Source>>) {
+        *self.source.borrow_mut() = source
+    }
+
+    pub fn report(&self, mut diagnostic: Diagnostic) {
+        if diagnostic.source.is_none() {
+            diagnostic.source = self.source.borrow().clone()
+        }
+
+        self.items.borrow_mut().push(diagnostic)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.items.borrow().iter().any(|d| d.is_error())
+    }
+
+    pub fn take(&self) -> Vec<Diagnostic> {
+        self.items.borrow_mut().drain(..).collect()
+    }
+}
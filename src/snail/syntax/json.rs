@@ -0,0 +1,90 @@
+use std::fmt::Write;
+
+use super::lexer::Span;
+use super::diagnostic::{Diagnostic, Source};
+use super::parser::SourceMap;
+
+// bumped whenever a field changes meaning or goes away, adding fields doesn't
+pub const JSON_VERSION: usize = 1;
+
+// the same rule for `.lua.map` files, which change separately from diagnostics
+pub const SOURCE_MAP_VERSION: usize = 1;
+
+fn string(text: &str) -> String {
+    let mut out = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            },
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+// lines and columns count from 1, columns in characters; bytes are a half-open
+// range from the start of the file, null when the source isn't known
+fn span(span: &Span, source: Option<&Source>) -> String {
+    let (byte_start, byte_end) = match source {
+        Some(s) => (format!("{}", s.offset(span.start)), format!("{}", s.offset(span.end))),
+        None    => ("null".to_owned(), "null".to_owned()),
+    };
+
+    format!(
+        "{{\"byte_start\":{},\"byte_end\":{},\"line_start\":{},\"column_start\":{},\"line_end\":{},\"column_end\":{}}}",
+        byte_start, byte_end, span.start.line, span.start.col + 1, span.end.line, span.end.col + 1
+    )
+}
+
+fn list<T, F: Fn(&T) -> String>(items: &[T], f: F) -> String {
+    format!("[{}]", items.iter().map(f).collect::<Vec<String>>().join(","))
+}
+
+// one diagnostic as a single line of json, see the README for the schema
+pub fn json(diagnostic: &Diagnostic) -> String {
+    let source = diagnostic.source.as_deref();
+
+    let file = match source {
+        Some(s) => string(&s.path),
+        None    => "null".to_owned(),
+    };
+
+    let primary = match diagnostic.span {
+        Some(ref s) => span(s, source),
+        None        => "null".to_owned(),
+    };
+
+    format!(
+        "{{\"version\":{},\"code\":{},\"severity\":{},\"message\":{},\"file\":{},\"span\":{},\"labels\":{},\"notes\":{},\"suggestions\":{}}}",
+        JSON_VERSION,
+        string(diagnostic.code),
+        string(&format!("{}", diagnostic.severity)),
+        string(&diagnostic.message),
+        file,
+        primary,
+        list(&diagnostic.labels, |l| format!("{{\"span\":{},\"message\":{}}}", span(&l.span, source), string(&l.message))),
+        list(&diagnostic.notes, |n| string(n)),
+        list(&diagnostic.suggestions, |s| format!("{{\"span\":{},\"replacement\":{},\"message\":{}}}", span(&s.span, source), string(&s.replacement), string(&s.message))),
+    )
+}
+
+// the `.lua.map` written next to compiled Lua, each mapping is a Lua line
+// followed by the snail line and column its code starts at, all counted from 1
+pub fn source_map(map: &SourceMap, lua: &str, source: &str) -> String {
+    format!(
+        "{{\"version\":{},\"file\":{},\"source\":{},\"mappings\":{}}}\n",
+        SOURCE_MAP_VERSION,
+        string(lua),
+        string(source),
+        list(&map.lines, |&(line, span)| format!("[{},{},{}]", line, span.start.line, span.start.col + 1)),
+    )
+}
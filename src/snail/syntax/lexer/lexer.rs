@@ -1,16 +1,19 @@
 use super::Tokenizer;
 use super::matcher::*;
-use super::token::{Token, TokenType};
+use super::token::{Token, TokenType, Span};
+use super::super::{Diagnostic, Diagnostics};
 
 use std::str::Chars;
 use std::rc::Rc;
 
-pub fn lexer(data: &mut Chars) -> Lexer {
-    let tokenizer = Tokenizer::new(data);
-    let mut lexer = Lexer::new(tokenizer);
+pub fn lexer(data: &mut Chars, diagnostics: &Rc<Diagnostics>) -> Lexer {
+    let tokenizer = Tokenizer::new(data, diagnostics);
+    let mut lexer = Lexer::new(tokenizer, diagnostics);
     
     let eol = vec!["\n"].iter().map(|&x| x.to_string()).collect();
 
+    let arrow = ["->"].iter().map(|&x| x.to_string()).collect();
+
     let symbols = vec![
         "(",
         ")",
@@ -32,20 +35,25 @@ pub fn lexer(data: &mut Chars) -> Lexer {
         "+",
         "-",
         "*",
-        "/",
+        "//",
         "/",
         "%",
         "^",
-        ">",
-        "<",
+        "<<",
+        ">>",
         ">=",
         "<=",
+        ">",
+        "<",
         "==",
         "!=",
+        "&",
+        "~",
+        "?",
     ].iter().map(|&x| x.to_string()).collect();
 
     let types = vec![
-        "num", "str", "idc", "bool",
+        "num", "int", "float", "str", "idc", "bool",
     ].iter().map(|&x| x.to_string()).collect();
 
     let boolean = vec![
@@ -53,12 +61,17 @@ pub fn lexer(data: &mut Chars) -> Lexer {
         "false",
     ].iter().map(|&x| x.to_string()).collect();
 
+    let nil = ["nil"].iter().map(|&x| x.to_string()).collect();
+
     let matcher_eol            = ConstantMatcher::new(TokenType::EOL, eol);
+    let matcher_arrow          = ConstantMatcher::new(TokenType::Symbol, arrow);
     let matcher_symbol         = ConstantMatcher::new(TokenType::Symbol, symbols);
     let matcher_operator       = ConstantMatcher::new(TokenType::Operator, operators);
     let matcher_boolean        = KeyMatcher::new(TokenType::BoolLiteral, boolean);
+    let matcher_nil            = KeyMatcher::new(TokenType::NilLiteral, nil);
     let matcher_types          = KeyMatcher::new(TokenType::Type, types);
     let matcher_whitespace     = WhitespaceMatcher {};
+    let matcher_comment        = CommentMatcher {};
     let matcher_int_literal    = IntLiteralMatcher {};
     let matcher_float_literal  = FloatLiteralMatcher {};
     let matcher_identifier     = IdentifierMatcher {};
@@ -66,28 +79,49 @@ pub fn lexer(data: &mut Chars) -> Lexer {
 
     lexer.matchers_mut().push(Rc::new(matcher_eol));
     lexer.matchers_mut().push(Rc::new(matcher_whitespace));
+    lexer.matchers_mut().push(Rc::new(matcher_comment));
+    lexer.matchers_mut().push(Rc::new(matcher_arrow));
     lexer.matchers_mut().push(Rc::new(matcher_operator));
     lexer.matchers_mut().push(Rc::new(matcher_symbol));
     lexer.matchers_mut().push(Rc::new(matcher_float_literal));
     lexer.matchers_mut().push(Rc::new(matcher_int_literal));
     lexer.matchers_mut().push(Rc::new(matcher_string_literal));
     lexer.matchers_mut().push(Rc::new(matcher_boolean));
+    lexer.matchers_mut().push(Rc::new(matcher_nil));
     lexer.matchers_mut().push(Rc::new(matcher_types));
     lexer.matchers_mut().push(Rc::new(matcher_identifier));
     lexer
 }
 
+// the comments a lexer skips over, for tools that read them
+pub fn comments(data: &mut Chars) -> Vec<Token> {
+    let mut lexer    = lexer(data, &Rc::new(Diagnostics::new()));
+    let mut comments = Vec::new();
+
+    while let Some(token) = lexer.match_token() {
+        match token.token_type {
+            TokenType::EOF     => break,
+            TokenType::Comment => comments.push(token),
+            _                  => (),
+        }
+    }
+
+    comments
+}
+
 pub struct Lexer {
     tokenizer: Tokenizer,
     matchers: Vec<Rc<Matcher>>,
+    diagnostics: Rc<Diagnostics>,
 }
 
 #[allow(dead_code)]
 impl Lexer {
-    pub fn new(tokenizer: Tokenizer) -> Lexer {
+    pub fn new(tokenizer: Tokenizer, diagnostics: &Rc<Diagnostics>) -> Lexer {
         Lexer {
             tokenizer,
             matchers: Vec::new(),
+            diagnostics: diagnostics.clone(),
         }
     }
 
@@ -117,13 +151,19 @@ impl Iterator for Lexer {
         let token = match self.match_token() {
             Some(n) => n,
             None    => {
-                println!("invalid token");
-                return None
+                let start = self.tokenizer.pos;
+                let c     = self.tokenizer.next().unwrap();
+
+                let span = Span::new(start, self.tokenizer.pos);
+                self.diagnostics.report(Diagnostic::error("E0003", &format!("invalid token: {}", c)).with_span(span));
+
+                return self.next()
             },
         };
         match token.token_type {
             TokenType::EOF => None,
-            TokenType::Whitespace => {
+            TokenType::Whitespace |
+            TokenType::Comment => {
                 match self.next() {
                     Some(t) => Some(t),
                     None => None,
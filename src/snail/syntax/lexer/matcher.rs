@@ -34,6 +34,25 @@ impl Matcher for WhitespaceMatcher {
     }
 }
 
+pub struct CommentMatcher;
+
+impl Matcher for CommentMatcher {
+    fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
+        if tokenizer.peek() != Some(&'#') {
+            return None
+        }
+
+        tokenizer.advance(1);
+
+        let mut accum = String::new();
+        while !tokenizer.end() && tokenizer.peek() != Some(&'\n') {
+            accum.push(tokenizer.next().unwrap());
+        }
+
+        token!(tokenizer, Comment, accum)
+    }
+}
+
 pub struct IntLiteralMatcher {}
 
 impl Matcher for IntLiteralMatcher {
@@ -52,15 +71,9 @@ impl Matcher for IntLiteralMatcher {
         }
         if !accum.is_empty() {
             let literal: String = if Some(false) == prefix {
-                match i64::from_str_radix(accum.as_str(), 10) {
-                    Ok(result) => format!("-{}", result),
-                    Err(error) => panic!("unable to parse int-literal: {}", error)
-                }
+                format!("-{}", accum)
             } else {
-                match u64::from_str_radix(accum.as_str(), 10) {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse int-literal: {}", error)
-                }
+                accum
             };
             token!(tokenizer, IntLiteral, literal)
         } else {
@@ -96,7 +109,8 @@ impl Matcher for FloatLiteralMatcher {
             let current = *tokenizer.peek().unwrap();
             if !current.is_whitespace() && current.is_digit(10) || current == '.' {
                 if current == '.' && accum.contains('.') {
-                    panic!("illegal decimal point")
+                    tokenizer.report("E0004", "illegal decimal point");
+                    break
                 }
                 accum.push(tokenizer.next().unwrap())
             } else {
@@ -108,30 +122,26 @@ impl Matcher for FloatLiteralMatcher {
             None
         } else if accum.contains('.') {
 
+            let result = match accum.parse::<f64>() {
+                Ok(result) => result,
+                Err(error) => {
+                    tokenizer.report("E0004", &format!("unable to parse float literal: {}", error));
+                    0.0
+                },
+            };
+
             let literal: String = if Some(false) == prefix {
-                match accum.parse::<f64>() {
-                    Ok(result) => format!("-{}", result),
-                    Err(error) => panic!("unable to parse float-literal: {}", error)
-                }
+                format!("-{}", result)
             } else {
-                match accum.parse::<f64>() {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse float-literal: {}", error)
-                }
+                result.to_string()
             };
 
             token!(tokenizer, FloatLiteral, literal)
         } else {
             let literal: String = if Some(false) == prefix {
-                match i64::from_str_radix(accum.as_str(), 10) {
-                    Ok(result) => format!("-{}", result),
-                    Err(error) => panic!("unable to parse int-literal: {}", error)
-                }
+                format!("-{}", accum)
             } else {
-                match u64::from_str_radix(accum.as_str(), 10) {
-                    Ok(result) => result.to_string(),
-                    Err(error) => panic!("unable to parse int-literal: {}", error)
-                }
+                accum
             };
 
             token!(tokenizer, IntLiteral, literal)
@@ -145,23 +155,25 @@ impl Matcher for StringLiteralMatcher {
     fn try_match(&self, tokenizer: &mut Tokenizer) -> Option<Token> {
         let mut raw_marker = false;
         let delimeter  = match tokenizer.peek().unwrap() {
-            &'"'  => Some('"'),
-            &'\'' => Some('\''),
-            &'r' => match *tokenizer.peek_n(1).unwrap() {
-                c @ '"' | c @ '\'' => {
+            &'"'  => '"',
+            &'\'' => '\'',
+            &'r' => match tokenizer.peek_n(1) {
+                Some(&c @ '"') | Some(&c @ '\'') => {
                     raw_marker = true;
                     tokenizer.advance(1); // Skips prefix
-                    Some(c)
+                    c
                 },
                 _ => return None,
             },
             _ => return None,
-        }.expect("invalid delimiter");
+        };
         tokenizer.advance(1); // Skips the opening delimeter
         let mut string       = String::new();
         let mut found_escape = false;
+        let mut closed       = false;
         loop {
-            if tokenizer.end() {
+            // strings don't span lines, so a missing delimeter stops at the end of one
+            if tokenizer.end() || tokenizer.peek() == Some(&'\n') {
                 break
             }
             if raw_marker {
@@ -174,7 +186,10 @@ impl Matcher for StringLiteralMatcher {
                         'n' => string.push_str("\\\\n"),
                         'r' => string.push_str("\\\\r"),
                         't' => string.push_str("\\\\t"),
-                        s => panic!("unwanted character escape: {}", s),
+                        s => {
+                            tokenizer.report("E0004", &format!("unknown character escape: \\{}", s));
+                            string.push(s)
+                        },
                     }
                     found_escape = false
                 } else {
@@ -183,7 +198,10 @@ impl Matcher for StringLiteralMatcher {
                             tokenizer.next();
                             found_escape = true
                         },
-                        &c if &c == &delimeter => break,
+                        &c if c == delimeter => {
+                            closed = true;
+                            break
+                        },
                         _ => string.push(tokenizer.next().unwrap()),
                     }
                 }
@@ -197,7 +215,10 @@ impl Matcher for StringLiteralMatcher {
                         'n' => string.push_str("\\n"),
                         'r' => string.push_str("\\r"),
                         't' => string.push_str("\\t"),
-                        s => panic!("unwanted character escape: {}", s),
+                        s => {
+                            tokenizer.report("E0004", &format!("unknown character escape: \\{}", s));
+                            string.push(s)
+                        },
                     }
                     found_escape = false
                 } else {
@@ -206,13 +227,20 @@ impl Matcher for StringLiteralMatcher {
                             tokenizer.next();
                             found_escape = true
                         },
-                        &c if &c == &delimeter => break,
+                        &c if c == delimeter => {
+                            closed = true;
+                            break
+                        },
                         _ => string.push(tokenizer.next().unwrap()),
                     }
                 }
             }
         }
-        tokenizer.advance(1); // Skips the closing delimeter
+        if closed {
+            tokenizer.advance(1); // Skips the closing delimeter
+        } else {
+            tokenizer.report("E0004", &format!("unterminated string literal, expected closing {}", delimeter));
+        }
         token!(tokenizer, StringLiteral, string)
     }
 }
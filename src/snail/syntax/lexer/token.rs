@@ -6,16 +6,18 @@ pub enum TokenType {
     FloatLiteral,
     StringLiteral,
     BoolLiteral,
+    NilLiteral,
     Symbol,
     Operator,
     Identifier,
     Type,
     Whitespace,
+    Comment,
     EOL,
     EOF,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct TokenPosition {
     pub line: usize,
     pub col:  usize,
@@ -44,11 +46,38 @@ impl TokenPosition {
     }
 }
 
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Span {
+    pub start: TokenPosition,
+    pub end:   TokenPosition,
+}
+
+impl Span {
+    pub fn new(start: TokenPosition, end: TokenPosition) -> Span {
+        Span {
+            start, end,
+        }
+    }
+
+    // from the start of this span to the end of another
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub position:   TokenPosition,
     content:        String,
+    // where the source the token was read from ends, escapes and all
+    end:            TokenPosition,
 }
 
 #[allow(dead_code)]
@@ -58,12 +87,22 @@ impl Token {
             token_type,
             position,
             content,
+            end: position,
         }
     }
 
+    pub fn ending_at(mut self, end: TokenPosition) -> Token {
+        self.end = end;
+        self
+    }
+
     pub fn content(&self) -> &String {
         &self.content
     }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.position, self.end)
+    }
 }
 
 impl<'a> PartialEq for Token {
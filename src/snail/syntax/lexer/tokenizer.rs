@@ -1,5 +1,8 @@
 use super::Matcher;
-use super::{Token, TokenType, TokenPosition};
+use super::{Token, TokenType, TokenPosition, Span};
+use super::super::{Diagnostic, Diagnostics};
+
+use std::rc::Rc;
 
 #[derive(Clone, Debug)]
 pub struct Snapshot {
@@ -22,6 +25,7 @@ pub struct Tokenizer {
     index:     usize,
     items:     Vec<char>,
     snapshots: Vec<Snapshot>,
+    diagnostics: Rc<Diagnostics>,
 }
 
 impl Iterator for Tokenizer {
@@ -34,15 +38,22 @@ impl Iterator for Tokenizer {
 
 #[allow(dead_code)]
 impl Tokenizer {
-    pub fn new(items: &mut Iterator<Item = char>) -> Tokenizer {
+    pub fn new(items: &mut dyn Iterator<Item = char>, diagnostics: &Rc<Diagnostics>) -> Tokenizer {
         Tokenizer {
             index:     0,
             pos:       TokenPosition::default(),
             items:     items.collect(),
             snapshots: Vec::new(),
+            diagnostics: diagnostics.clone(),
         }
     }
 
+    // reports a problem with the token being matched
+    pub fn report(&self, code: &'static str, message: &str) {
+        let span = Span::new(self.last_position(), self.pos);
+        self.diagnostics.report(Diagnostic::error(code, message).with_span(span))
+    }
+
     pub fn end(&self) -> bool {
         self.end_n(0)
     }
@@ -118,7 +129,7 @@ impl Tokenizer {
         match matcher.try_match(self) {
             Some(t) => {
                 self.commit_snapshot();
-                Some(t)
+                Some(t.ending_at(self.pos))
             }
 
             None => {
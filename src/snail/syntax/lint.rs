@@ -0,0 +1,319 @@
+use std::rc::Rc;
+
+use super::lexer::{Token, Span, TokenPosition};
+use super::parser::{Expression, Statement};
+use super::{SymTab, Diagnostic, Diagnostics, Severity};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    UnusedDefinition,
+    UnusedParameter,
+    Shadowing,
+    HostShadowing,
+}
+
+impl Rule {
+    pub fn all() -> [Rule; 4] {
+        [Rule::UnusedDefinition, Rule::UnusedParameter, Rule::Shadowing, Rule::HostShadowing]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Rule::UnusedDefinition => "unused_definition",
+            Rule::UnusedParameter  => "unused_parameter",
+            Rule::Shadowing        => "shadowing",
+            Rule::HostShadowing    => "host_shadowing",
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Rule::UnusedDefinition => "W0001",
+            Rule::UnusedParameter  => "W0002",
+            Rule::Shadowing        => "W0003",
+            Rule::HostShadowing    => "W0004",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        Rule::all().iter().find(|r| r.name() == name).cloned()
+    }
+}
+
+// the level of every rule, all warnings unless configured otherwise
+#[derive(Debug, Clone)]
+pub struct Levels {
+    levels: Vec<(Rule, Level)>,
+}
+
+impl Default for Levels {
+    fn default() -> Levels {
+        Levels {
+            levels: Rule::all().iter().map(|r| (*r, Level::Warn)).collect(),
+        }
+    }
+}
+
+impl Levels {
+    pub fn set(&mut self, rule: Rule, level: Level) {
+        for &mut (r, ref mut l) in self.levels.iter_mut() {
+            if r == rule {
+                *l = level
+            }
+        }
+    }
+
+    pub fn get(&self, rule: Rule) -> Level {
+        self.levels.iter().find(|&&(r, _)| r == rule).map_or(Level::Warn, |&(_, l)| l)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Definition,
+    Parameter,
+    Exported,
+}
+
+struct Entry {
+    name: Rc<String>,
+    span: Span,
+    kind: Kind,
+    used: bool,
+}
+
+// `# allow(rule, ...)` silences rules on its own line and the line after it
+fn suppressions(comments: &[Token]) -> Vec<(usize, Rule)> {
+    let mut allowed = Vec::new();
+
+    for c in comments {
+        let text = c.content().trim();
+
+        if !text.starts_with("allow(") || !text.ends_with(')') {
+            continue
+        }
+
+        for name in text["allow(".len() .. text.len() - 1].split(',') {
+            if let Some(rule) = Rule::from_name(name.trim()) {
+                allowed.push((c.position.line, rule));
+                allowed.push((c.position.line + 1, rule))
+            }
+        }
+    }
+
+    allowed
+}
+
+pub struct Linter {
+    levels:  Levels,
+    allowed: Vec<(usize, Rule)>,
+    scopes:  Vec<Vec<Entry>>,
+    lints:   Vec<Diagnostic>,
+}
+
+impl Linter {
+    pub fn new(levels: Levels, comments: &[Token]) -> Linter {
+        Linter {
+            levels,
+            allowed: suppressions(comments),
+            scopes:  Vec::new(),
+            lints:   Vec::new(),
+        }
+    }
+
+    // lints a program against the host globals already declared in `host`
+    pub fn lint(mut self, statements: &[Statement], host: &Rc<SymTab>, diagnostics: &Diagnostics) {
+        let sym = Rc::new(SymTab::new(host.clone(), &[]));
+
+        self.scopes.push(Vec::new());
+
+        for s in statements {
+            if let Statement::Definition(binding, _, ref id, Some(_)) = *s {
+                self.declare(&sym, id, binding.span, Kind::Exported)
+            }
+        }
+
+        for s in statements {
+            self.statement(s, &sym)
+        }
+
+        self.leave();
+
+        self.lints.sort_by_key(|l| l.span.map(|s| (s.start.line, s.start.col)));
+
+        for l in self.lints {
+            diagnostics.report(l)
+        }
+    }
+
+    fn report(&mut self, rule: Rule, span: Span, message: String) {
+        self.report_fix(rule, span, message, None)
+    }
+
+    fn report_fix(&mut self, rule: Rule, span: Span, message: String, fix: Option<(&str, &str)>) {
+        let severity = match self.levels.get(rule) {
+            Level::Allow => return,
+            Level::Warn  => Severity::Warning,
+            Level::Deny  => Severity::Error,
+        };
+
+        if self.allowed.contains(&(span.start.line, rule)) {
+            return
+        }
+
+        let lint = Diagnostic::new(severity, rule.code(), &message)
+            .with_span(span)
+            .with_note(&format!("'# allow({})' on this line or the one above silences this", rule.name()));
+
+        self.lints.push(match fix {
+            Some((replacement, message)) => lint.with_suggestion(span, replacement, message),
+            None                         => lint,
+        })
+    }
+
+    fn declare(&mut self, sym: &Rc<SymTab>, name: &Rc<String>, span: Span, kind: Kind) {
+        if **name == "_" {
+            return
+        }
+
+        match sym.get_name(name) {
+            // another arm of a definition already in this scope
+            Some((_, 0)) => return,
+            Some((_, env_index)) if env_index >= self.scopes.len() => {
+                self.report(Rule::HostShadowing, span, format!("{}: shadows a host global", name))
+            },
+            Some(_) => self.report(Rule::Shadowing, span, format!("{}: shadows an outer binding", name)),
+            None    => (),
+        }
+
+        sym.add_name(name);
+
+        self.scopes.last_mut().unwrap().push(Entry { name: name.clone(), span, kind, used: false })
+    }
+
+    fn declare_variant(&mut self, sym: &Rc<SymTab>, name: &Rc<String>) {
+        if sym.get_name(name).is_some_and(|(_, env_index)| env_index == 0) {
+            return
+        }
+
+        sym.add_name(name);
+
+        self.scopes.last_mut().unwrap().push(Entry { name: name.clone(), span: Span::default(), kind: Kind::Exported, used: true })
+    }
+
+    fn use_name(&mut self, sym: &Rc<SymTab>, name: &str) {
+        if let Some((i, env_index)) = sym.get_name(name) {
+            if env_index < self.scopes.len() {
+                let depth = self.scopes.len() - 1 - env_index;
+                self.scopes[depth][i].used = true
+            }
+        }
+    }
+
+    fn leave(&mut self) {
+        for e in self.scopes.pop().unwrap() {
+            if e.used || e.name.starts_with('_') {
+                continue
+            }
+
+            match e.kind {
+                Kind::Definition => self.report(Rule::UnusedDefinition, e.span, format!("{}: defined but never used", e.name)),
+                Kind::Parameter  => self.report_fix(Rule::UnusedParameter, e.span, format!("{}: parameter is never used, name it '_' if that's intended", e.name), Some(("_", "name it '_'"))),
+                Kind::Exported   => (),
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, sym: &Rc<SymTab>) {
+        match *statement {
+            Statement::Expression(ref e) => self.expression(e, sym),
+            Statement::Data(ref data) => for v in data.variants.iter() {
+                self.declare_variant(sym, &v.name)
+            },
+            Statement::Definition(binding, _, ref id, ref e) => {
+                self.declare(sym, id, binding.span, Kind::Definition);
+
+                if let Some(ref e) = *e {
+                    self.expression(e, sym)
+                }
+            },
+            Statement::Interface(_) => (),
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Expression, sym: &Rc<SymTab>) {
+        match *pattern {
+            // just the name, so fixes leave the type and default alone
+            Expression::Param { ref name, span, .. } => {
+                let end = TokenPosition::new(span.start.line, span.start.col + name.chars().count());
+                self.declare(sym, name, Span::new(span.start, end), Kind::Parameter)
+            },
+            Expression::Variant(_, ref fields, _) => for f in fields {
+                self.pattern(f, sym)
+            },
+            _ => (),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, sym: &Rc<SymTab>) {
+        match *expression {
+            Expression::Identifier(ref n, _) => self.use_name(sym, n),
+            Expression::Block(ref statements, _) => {
+                let local_sym = Rc::new(SymTab::new(sym.clone(), &[]));
+                self.scopes.push(Vec::new());
+
+                for s in statements {
+                    self.statement(s, &local_sym)
+                }
+
+                self.leave()
+            },
+            Expression::Arm(ref params, ref body, _) => {
+                let local_sym = Rc::new(SymTab::new(sym.clone(), &[]));
+                self.scopes.push(Vec::new());
+
+                for p in params {
+                    self.pattern(p, &local_sym)
+                }
+
+                self.expression(body, &local_sym);
+                self.leave()
+            },
+            // assigning to a name doesn't read it
+            Expression::Assignment(ref target, ref value, _) => {
+                if let Expression::Field(ref object, _, _) = **target {
+                    self.expression(object, sym)
+                }
+
+                self.expression(value, sym)
+            },
+            Expression::Operation { ref left, ref right, .. } => {
+                self.expression(left, sym);
+                self.expression(right, sym)
+            },
+            Expression::Call(ref callee, ref args, _) => {
+                self.expression(callee, sym);
+
+                for a in args.iter() {
+                    self.expression(a, sym)
+                }
+            },
+            Expression::Variant(_, ref args, _) => for a in args {
+                self.expression(a, sym)
+            },
+            Expression::Named(_, ref e, _) |
+            Expression::Field(ref e, _, _) => self.expression(e, sym),
+            Expression::Record(ref fields, _) => for (_, e) in fields.iter() {
+                self.expression(e, sym)
+            },
+            _ => (),
+        }
+    }
+}
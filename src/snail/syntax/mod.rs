@@ -1,13 +1,19 @@
 pub mod lexer;
 pub mod parser;
-pub mod error;
+pub mod diagnostic;
 pub mod symtab;
 pub mod typetab;
-
-pub type RunResult<T> = Result<T, RunError>;
+pub mod lint;
+pub mod render;
+pub mod json;
+pub mod catalog;
 
 pub use self::parser::*;
 pub use self::lexer::*;
 pub use self::symtab::*;
 pub use self::typetab::*;
-pub use self::error::*;
+pub use self::diagnostic::*;
+pub use self::lint::*;
+pub use self::render::*;
+pub use self::json::*;
+pub use self::catalog::*;
@@ -1,341 +1,2123 @@
-use super::{ParserResult, ParserError};
+use super::{ParserResult, Diagnostic, Diagnostics, Emitter, Options, SourceMap, Target};
+use super::lexer::Span;
 use super::super::{SymTab, TypeTab};
 
 use std::rc::Rc;
 use std::fmt;
+use std::fmt::Write;
 
+// every node ends with the span of the source it was parsed from
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
-    Block(Vec<Statement>),
-    Number(f64),
-    Bool(bool),
-    Str(Rc<String>),
-    Identifier(Rc<String>),
-    Assignment(Rc<Expression>, Rc<Expression>),
+    Block(Vec<Statement>, Span),
+    Int(i64, Span),
+    Float(f64, Span),
+    Bool(bool, Span),
+    Nil(Span),
+    Str(Rc<String>, Span),
+    Identifier(Rc<String>, Span),
+    Assignment(Rc<Expression>, Rc<Expression>, Span),
     Operation {
         left:  Rc<Expression>,
         op:    Operand,
         right: Rc<Expression>,
+        span:  Span,
     },
-    Arm(Vec<Rc<Expression>>, Rc<Expression>),
-    Call(Rc<Expression>, Rc<Vec<Expression>>),
+    Arm(Vec<Rc<Expression>>, Rc<Expression>, Span),
+    Param {
+        name:    Rc<String>,
+        t:       Option<Type>,
+        default: Option<Rc<Expression>>,
+        span:    Span,
+    },
+    Variant(Rc<String>, Vec<Rc<Expression>>, Span),
+    Call(Rc<Expression>, Rc<Vec<Expression>>, Span),
+    Named(Rc<String>, Rc<Expression>, Span),
+    Record(Vec<(Rc<String>, Rc<Expression>)>, Span),
+    Field(Rc<Expression>, Rc<String>, Span),
     EOF,
 }
 
 #[allow(dead_code)]
 impl Expression {
+    pub fn span(&self) -> Span {
+        match *self {
+            Expression::Block(_, span)         |
+            Expression::Int(_, span)           |
+            Expression::Float(_, span)         |
+            Expression::Bool(_, span)          |
+            Expression::Nil(span)              |
+            Expression::Str(_, span)           |
+            Expression::Identifier(_, span)    |
+            Expression::Assignment(_, _, span) |
+            Expression::Operation { span, .. } |
+            Expression::Arm(_, _, span)        |
+            Expression::Param { span, .. }     |
+            Expression::Variant(_, _, span)    |
+            Expression::Call(_, _, span)       |
+            Expression::Named(_, _, span)      |
+            Expression::Record(_, span)        |
+            Expression::Field(_, _, span)      => span,
+            Expression::EOF                    => Span::default(),
+        }
+    }
+
+    // structural equality, ignoring where either side was written
+    pub fn same(&self, other: &Expression) -> bool {
+        fn all(a: &[Rc<Expression>], b: &[Rc<Expression>]) -> bool {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.same(b))
+        }
+
+        match (self, other) {
+            (Expression::Block(a, _), Expression::Block(b, _)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.same(b))
+            },
+            (Expression::Int(a, _), Expression::Int(b, _))                 => a == b,
+            (Expression::Float(a, _), Expression::Float(b, _))             => a == b,
+            (Expression::Bool(a, _), Expression::Bool(b, _))               => a == b,
+            (Expression::Nil(_), Expression::Nil(_))                       => true,
+            (Expression::Str(a, _), Expression::Str(b, _))                 |
+            (Expression::Identifier(a, _), Expression::Identifier(b, _))   => a == b,
+            (Expression::Assignment(a, x, _), Expression::Assignment(b, y, _)) => a.same(b) && x.same(y),
+            (Expression::Operation { left: a, op: o, right: x, .. },
+             Expression::Operation { left: b, op: p, right: y, .. }) => o == p && a.same(b) && x.same(y),
+            (Expression::Arm(a, x, _), Expression::Arm(b, y, _)) => all(a, b) && x.same(y),
+            (Expression::Param { name: a, t: x, default: d, .. },
+             Expression::Param { name: b, t: y, default: e, .. }) => a == b && x == y && match (d, e) {
+                (Some(d), Some(e)) => d.same(e),
+                (None, None)       => true,
+                _                  => false,
+            },
+            (Expression::Variant(a, x, _), Expression::Variant(b, y, _)) => a == b && all(x, y),
+            (Expression::Call(a, x, _), Expression::Call(b, y, _)) => {
+                a.same(b) && x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| x.same(y))
+            },
+            (Expression::Named(a, x, _), Expression::Named(b, y, _)) => a == b && x.same(y),
+            (Expression::Record(a, _), Expression::Record(b, _)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|((m, x), (n, y))| m == n && x.same(y))
+            },
+            (Expression::Field(a, m, _), Expression::Field(b, n, _)) => m == n && a.same(b),
+            (Expression::EOF, Expression::EOF) => true,
+            _                                  => false,
+        }
+    }
+
     pub fn get_type(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> ParserResult<Type> {
         match *self {
-            Expression::Number(_) => Ok(Type::Num),
-            Expression::Str(_)    => Ok(Type::Str),
-            Expression::Bool(_)   => Ok(Type::Bool),
-            Expression::Identifier(ref n) => match sym.get_name(&*n) {
+            Expression::Int(..)   => Ok(Type::Int),
+            Expression::Float(..) => Ok(Type::Float),
+            Expression::Str(..)   => Ok(Type::Str),
+            Expression::Bool(..)  => Ok(Type::Bool),
+            Expression::Nil(_)    => Ok(Type::Option(Rc::new(env.fresh()))),
+            Expression::Identifier(ref n, span) => match sym.get_name(n) {
                 Some((i, env_index)) => {
-                    Ok(env.get_type(i, env_index).unwrap())
+                    Ok(env.instantiate(&env.resolve(&env.get_type(i, env_index).unwrap())))
                 },
-                None => Err(ParserError::new(&format!("unexpected use of: {}", n))),
-            },
-            Expression::Assignment(ref id, _) => id.get_type(&sym, &env),
-            Expression::Call(ref id, _) => match id.get_type(sym, env)? {
-                Type::Any | Type::Undefined => Ok(Type::Any),
-                Type::Block(ref t) => {
-                    let ref t = **t;
-                    Ok(t.clone())
-                },
-                t => Err(ParserError::new(&format!("{}: can't call {:?}", id, t))),
+                None => Err(Box::new(Diagnostic::error("E0101", &format!("unexpected use of: {}", n)).with_span(span))),
+            },
+            Expression::Assignment(ref target, ref value, _) => {
+                let t = match **target {
+                    Expression::Identifier(ref n, span) => match sym.get_name(n) {
+                        Some((i, env_index)) => {
+                            if !sym.is_mutable(n) {
+                                return Err(Box::new(Diagnostic::error("E0212", &format!("{}: can't reassign immutable binding", n)).with_span(span).with_note("declare it with 'mut' to allow reassignment")))
+                            }
+
+                            env.get_type(i, env_index).unwrap()
+                        },
+                        None => return Err(Box::new(Diagnostic::error("E0101", &format!("{}: assignment to undeclared name", n)).with_span(span))),
+                    },
+                    Expression::Field(..) => target.get_type(sym, env)?,
+                    _                     => return Err(Box::new(Diagnostic::error("E0213", "can't assign to this expression").with_span(target.span()))),
+                };
+
+                let found = value.get_type(sym, env)?;
+
+                if env.unify_at(&t, &found, value.span()).is_err() {
+                    let error = Diagnostic::error("E0201", &about(target, &format!("expected '{:?}', got '{:?}'", env.resolve(&t), env.resolve(&found)))).with_span(value.span());
+                    return Err(inferred(error, target.origin(sym, env), &env.resolve(&t)))
+                }
+
+                Ok(env.resolve(&t))
+            },
+            Expression::Call(ref id, ref args, _) => {
+                let mut arg_types = Vec::new();
+
+                for a in args.iter() {
+                    arg_types.push(a.get_type(sym, env)?)
+                }
+
+                let callee = match **id {
+                    Expression::Field(ref object, ref name, span) => match env.resolve(&object.get_type(sym, env)?) {
+                        t @ Type::Interface(_) => t.member(name).map_err(|e| Diagnostic::error("E0206", &about(id, &e)).with_span(span))?,
+                        t                      => t.member(name).and_then(|m| m.method()).map_err(|e| Diagnostic::error("E0206", &about(id, &e)).with_span(span))?,
+                    },
+                    _ => id.get_type(sym, env)?,
+                };
+
+                match callee {
+                    Type::Any | Type::Undefined => Ok(Type::Any),
+                    t @ Type::Var(_) => {
+                        let ret = env.fresh();
+                        let sig = Signature::new(arg_types.clone(), arg_types.len(), false, ret.clone());
+
+                        env.unify_at(&t, &Type::Block(Rc::new(sig)), self.span()).map_err(|e| Diagnostic::error("E0201", &about(id, &e)).with_span(self.span()))?;
+
+                        Ok(env.resolve(&ret))
+                    },
+                    Type::Block(ref sig) => {
+                        if let Err((arg, e)) = sig.check(&arg_types) {
+                            // an argument that only fits once narrowed is a nil-safety error
+                            let code = match arg.map(|i| (&sig.params[i], &arg_types[i])) {
+                                Some((p, Type::Option(inner))) if p.compare(inner) => "E0203",
+                                _                                                  => "E0202",
+                            };
+
+                            let error = Diagnostic::error(code, &about(id, &e));
+
+                            // the argument that doesn't fit, or the whole call when the count is off
+                            return Err(match arg {
+                                Some(i) => inferred(error.with_span(args[i].span()), sig.origin(i), &sig.params[i]),
+                                None    => Box::new(error.with_span(self.span())),
+                            })
+                        }
+
+                        for (i, (p, a)) in sig.params.iter().zip(arg_types.iter()).enumerate() {
+                            env.unify_at(p, a, args[i].span()).map_err(|e| {
+                                inferred(Diagnostic::error("E0201", &about(id, &format!("argument {}: {}", i + 1, e))).with_span(args[i].span()), sig.origin(i), p)
+                            })?
+                        }
+
+                        Ok(env.resolve(&sig.ret))
+                    },
+                    Type::Overload(ref sigs) => {
+                        let mut cases: Vec<Vec<Type>> = vec![Vec::new()];
+
+                        for a in arg_types.iter() {
+                            let mut next = Vec::new();
+
+                            for case in cases.iter() {
+                                for m in env.resolve(a).members() {
+                                    let mut case = case.clone();
+                                    case.push(m);
+                                    next.push(case)
+                                }
+                            }
+
+                            cases = next
+                        }
+
+                        let mut rets = Vec::new();
+
+                        for case in cases.iter() {
+                            let mut accepted = false;
+
+                            for sig in sigs.iter() {
+                                if sig.check(case).is_ok() {
+                                    accepted = true;
+                                    rets.push(env.resolve(&sig.ret))
+                                }
+                            }
+
+                            if !accepted {
+                                return Err(Box::new(Diagnostic::error("E0211", &about(id, &format!("no arm accepts {:?}", case))).with_span(self.span())))
+                            }
+                        }
+
+                        if rets.iter().any(|r| r.has_vars()) {
+                            if rets.iter().all(|r| *r == rets[0]) {
+                                Ok(rets[0].clone())
+                            } else {
+                                Ok(Type::Any)
+                            }
+                        } else {
+                            Ok(Type::union(rets))
+                        }
+                    },
+                    t => Err(Box::new(Diagnostic::error("E0205", &about(id, &format!("can't call {:?}", t))).with_span(id.span()))),
+                }
+            },
+            Expression::Operation { ref left, ref op, ref right, .. } => {
+                let l = left.get_type(sym, env)?;
+                let r = right.get_type(sym, env)?;
+
+                match *op {
+                    Operand::Coalesce => {
+                        let inner = match env.resolve(&l) {
+                            Type::Option(t) => (*t).clone(),
+                            t               => t,
+                        };
+
+                        env.unify_at(&inner, &r, self.span()).map_err(|e| inferred(Diagnostic::error("E0201", &about(self, &e)).with_span(right.span()), left.origin(sym, env), &inner))?;
+
+                        return Ok(env.resolve(&inner))
+                    },
+                    Operand::Equal | Operand::NEqual => (),
+                    _ => for (e, t) in [(left, &l), (right, &r)] {
+                        if let Type::Option(_) = env.resolve(t) {
+                            return Err(Box::new(Diagnostic::error("E0203", &e.subject().map_or("operand is possibly nil".to_owned(), |s| format!("{} is possibly nil", s))).with_span(e.span())))
+                        }
+                    },
+                }
+
+                if op.is_numeric() {
+                    let number = if op.is_integral() { Type::Int } else { Type::Num };
+
+                    match (env.resolve(&l), env.resolve(&r)) {
+                        (Type::Var(_), Type::Var(_)) => {
+                            env.unify_at(&l, &number, self.span()).map_err(|e| Diagnostic::error("E0204", &about(left, &e)).with_span(left.span()))?;
+                            env.unify_at(&r, &number, self.span()).map_err(|e| Diagnostic::error("E0204", &about(right, &e)).with_span(right.span()))?;
+                        },
+                        (Type::Var(_), ref t) if t.is_number() => {
+                            env.unify_at(&l, &number, self.span()).map_err(|e| Diagnostic::error("E0204", &about(left, &e)).with_span(left.span()))?
+                        },
+                        (ref t, Type::Var(_)) if t.is_number() => {
+                            env.unify_at(&r, &number, self.span()).map_err(|e| Diagnostic::error("E0204", &about(right, &e)).with_span(right.span()))?
+                        },
+                        _ => (),
+                    }
+                }
+
+                let (lt, rt) = (env.resolve(&l).or_any(), env.resolve(&r).or_any());
+
+                op.operate((lt.clone(), rt.clone())).map_err(|e| {
+                    // both operands are named, either could be the one that's wrong
+                    let error = Diagnostic::error("E0204", &about(self, &e))
+                        .with_span(self.span())
+                        .with_label(left.span(), &format!("'{:?}' here", lt))
+                        .with_label(right.span(), &format!("'{:?}' here", rt));
+
+                    match left.origin(sym, env) {
+                        Some(_) => inferred(error, left.origin(sym, env), &env.resolve(&l)),
+                        None    => inferred(error, right.origin(sym, env), &env.resolve(&r)),
+                    }
+                })
             },
-            Expression::Operation { ref left, ref op, ref right, } => Ok(op.operate((left.get_type(sym, env)?, right.get_type(sym, env)?))?),
-            Expression::Block(ref statements) => {
+            Expression::Block(ref statements, _) => {
                 let local_sym = Rc::new(SymTab::new(sym.clone(), &[]));
                 let local_env = Rc::new(TypeTab::new(env.clone(), &Vec::new()));
 
-                let mut acc = 1;
-                for s in statements {
-                    if acc == statements.len() {
-                        match *s {
-                            Statement::Expression(ref e) => return Ok(Type::Block(Rc::new(e.get_type(&local_sym, &local_env)?))),
-                            _                            => return Err(ParserError::new(&format!("missing return value"))),
-                        }
+                let mut arms     = Vec::new();
+                let mut patterns = Vec::new();
+                let mut last     = None;
+
+                for s in statements {
+                    match *s {
+                        Statement::Expression(ref e) => match **e {
+                            Expression::Arm(ref params, _, _) => {
+                                patterns.push(params);
+
+                                let sig = env.resolve_signature(&e.signature(&local_sym, &local_env)?);
+
+                                if !arms.contains(&sig) {
+                                    arms.push(sig)
+                                }
+
+                                last = None
+                            },
+                            _ => last = Some(e.get_type(&local_sym, &local_env)?),
+                        },
+                        _ => {
+                            s.visit(&local_sym, &local_env)?;
+                            last = None
+                        },
+                    }
+                }
+
+                check_exhaustive(&patterns, env).map_err(|e| e.at(self.span()))?;
+
+                match arms.len() {
+                    0 => match last {
+                        Some(ret) => Ok(Type::Block(Rc::new(Signature::new(Vec::new(), 0, false, ret)))),
+                        None      => Err(Box::new(Diagnostic::error("E0208", "missing return value").with_span(self.span()))),
+                    },
+                    1 => Ok(Type::Block(Rc::new(arms.pop().unwrap()))),
+                    _ => Ok(Type::Overload(Rc::new(arms))),
+                }
+            }
+            Expression::Arm(..) => Ok(self.signature(sym, env)?.ret),
+            Expression::Record(ref fields, _) => {
+                let mut types: Vec<(Rc<String>, Type)> = Vec::new();
+
+                for (name, value) in fields.iter() {
+                    if types.iter().any(|(n, _)| n == name) {
+                        return Err(Box::new(Diagnostic::error("E0209", &format!("{}: field given more than once", name)).with_span(value.span())))
+                    }
+
+                    types.push((name.clone(), value.get_type(sym, env)?))
+                }
+
+                Ok(Type::Record(Rc::new(types)))
+            },
+            Expression::Field(ref object, ref name, _) => {
+                let t = env.resolve(&object.get_type(sym, env)?);
+
+                Ok(t.member(name).map_err(|e| Diagnostic::error("E0206", &about(self, &e)).with_span(self.span()))?)
+            },
+            _ => Ok(Type::Undefined),
+        }
+    }
+
+    // how messages refer to a name or a field of one, other nodes go unnamed
+    pub fn subject(&self) -> Option<String> {
+        match *self {
+            Expression::Identifier(ref n, _)           => Some(n.to_string()),
+            Expression::Field(ref object, ref name, _) => object.subject().map(|o| format!("{}.{}", o, name)),
+            _                                          => None,
+        }
+    }
+
+    // where inference fixed the type of the name this reads, if it did
+    pub fn origin(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> Option<Span> {
+        match *self {
+            Expression::Identifier(ref n, _) => sym.get_name(n).and_then(|(i, env_index)| env.get_type(i, env_index).ok()).and_then(|t| env.origin(&t)),
+            _                                => None,
+        }
+    }
+
+    pub fn signature(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> ParserResult<Signature> {
+        match *self {
+            Expression::Arm(ref params, ref body, _) => {
+                let mut param_names = Vec::new();
+                let mut param_types = Vec::new();
+                let mut types       = Vec::new();
+                let mut required    = 0;
+
+                for p in params {
+                    let t = p.bind_pattern(env, &mut param_names, &mut param_types)?;
+
+                    types.push(t);
+
+                    match **p {
+                        Expression::Param { default: Some(_), .. } => (),
+                        _                                          => required = types.len(),
+                    }
+                }
+
+                let local_sym = Rc::new(SymTab::new(sym.clone(), param_names.as_slice()));
+                let local_env = Rc::new(TypeTab::new(env.clone(), &param_types));
+
+                let ret = body.get_type(&local_sym, &local_env)?;
+
+                Ok(env.resolve_signature(&Signature::new(types, required, false, ret)))
+            },
+            _ => Err(Box::new(Diagnostic::error("E0205", &about(self, "not an arm")).with_span(self.span()))),
+        }
+    }
+
+    pub fn bind_pattern(&self, env: &Rc<TypeTab>, names: &mut Vec<Rc<String>>, types: &mut Vec<Type>) -> ParserResult<Type> {
+        match *self {
+            Expression::Param { ref name, ref t, .. } => {
+                let t = t.as_ref().map(|t| env.expand(t)).unwrap_or_else(|| env.fresh());
+
+                names.push(name.clone());
+                types.push(t.clone());
+
+                Ok(t)
+            },
+            Expression::Variant(ref name, ref fields, span) => {
+                let (data, index) = match env.get_variant(name) {
+                    Some(v) => v,
+                    None    => return Err(Box::new(Diagnostic::error("E0103", &format!("{}: undeclared variant", name)).with_span(span))),
+                };
+
+                let variant = &data.variants[index];
+
+                if variant.fields.len() != fields.len() {
+                    return Err(Box::new(Diagnostic::error("E0214", &format!("{}: expected {} field(s), got {}", name, variant.fields.len(), fields.len())).with_span(span)))
+                }
+
+                let (t, field_types) = match env.instantiate(&data.constructor(index)) {
+                    Type::Block(ref sig) => (sig.ret.clone(), sig.params.clone()),
+                    t                    => (t, Vec::new()),
+                };
+
+                for (field, expected) in fields.iter().zip(field_types.iter()) {
+                    let found = field.bind_pattern(env, names, types)?;
+
+                    env.unify_at(expected, &found, field.span()).map_err(|e| Diagnostic::error("E0201", &format!("{}: {}", name, e)).with_span(field.span()))?
+                }
+
+                Ok(t)
+            },
+            Expression::Int(..)   => Ok(Type::Int),
+            Expression::Float(..) => Ok(Type::Float),
+            Expression::Str(..)   => Ok(Type::Str),
+            Expression::Bool(..)  => Ok(Type::Bool),
+            Expression::Nil(_)    => Ok(Type::Option(Rc::new(env.fresh()))),
+            _                     => Ok(Type::Any),
+        }
+    }
+
+    pub fn pattern_names(&self, names: &mut Vec<Rc<String>>) {
+        match *self {
+            Expression::Param { ref name, .. } => names.push(name.clone()),
+            Expression::Variant(_, ref fields, _) => for field in fields {
+                field.pattern_names(names)
+            },
+            _ => (),
+        }
+    }
+
+    pub fn is_irrefutable(&self) -> bool {
+        matches!(*self, Expression::Param { t: None, .. })
+    }
+
+    fn lua_pattern(&self, path: &str, f: &mut Emitter, checks: &mut Vec<String>, bindings: &mut Vec<String>) -> fmt::Result {
+        match *self {
+            Expression::Param { ref name, ref t, .. } => {
+                if let Some(ref t) = *t {
+                    let tests: Option<Vec<String>> = t.members().iter().map(|m| m.lua_check(path, f.options.target)).collect();
+
+                    if let Some(tests) = tests {
+                        if tests.len() == 1 {
+                            checks.push(tests[0].clone())
+                        } else {
+                            checks.push(format!("({})", tests.join(" or ")))
+                        }
+                    }
+                }
+
+                let name = f.name(name);
+
+                if name != path {
+                    bindings.push(format!("local {} = {}", name, path))
+                }
+            },
+            Expression::Variant(ref name, ref fields, _) => {
+                checks.push(format!(r#"type({}) == "table""#, path));
+                checks.push(format!(r#"{}.tag == "{}""#, path, name));
+
+                for (i, field) in fields.iter().enumerate() {
+                    field.lua_pattern(&format!("{}[{}]", path, i + 1), f, checks, bindings)?
+                }
+            },
+            ref c => {
+                let literal = f.fragment(|f| c.lua(f))?;
+                checks.push(format!("{} == {}", literal, path))
+            },
+        }
+
+        Ok(())
+    }
+
+    // deferred references sit inside a block and only run once it is called
+    pub fn references(&self, deferred: bool, acc: &mut Vec<(Rc<String>, Span, bool)>) {
+        match *self {
+            Expression::Identifier(ref n, span) => acc.push((n.clone(), span, deferred)),
+            Expression::Block(ref statements, _) => for s in statements {
+                s.references(true, acc)
+            },
+            Expression::Arm(ref params, ref body, _) => {
+                for p in params {
+                    p.references(deferred, acc)
+                }
+                body.references(deferred, acc)
+            },
+            Expression::Param { default: Some(ref d), .. } => d.references(deferred, acc),
+            Expression::Assignment(ref l, ref r, _) |
+            Expression::Operation { left: ref l, right: ref r, .. } => {
+                l.references(deferred, acc);
+                r.references(deferred, acc)
+            },
+            Expression::Call(ref id, ref args, _) => {
+                id.references(deferred, acc);
+                for a in args.iter() {
+                    a.references(deferred, acc)
+                }
+            },
+            Expression::Named(_, ref e, _) |
+            Expression::Field(ref e, _, _) => e.references(deferred, acc),
+            Expression::Record(ref fields, _) => for (_, e) in fields.iter() {
+                e.references(deferred, acc)
+            },
+            _ => (),
+        }
+    }
+
+    // checks every use of a name against the scopes it can see
+    pub fn resolve(&self, sym: &Rc<SymTab>) -> ParserResult<()> {
+        match *self {
+            Expression::Identifier(ref n, span) => match sym.get_name(n) {
+                Some(_) => Ok(()),
+                None    => {
+                    let suggestions = sym.suggest(n);
+
+                    let hint = match suggestions.len() {
+                        0 => String::new(),
+                        _ => format!(", did you mean {}?", suggestions.iter().map(|s| format!("'{}'", s)).collect::<Vec<String>>().join(" or ")),
+                    };
+
+                    let mut error = Diagnostic::error("E0101", &format!("{}: undeclared{}", n, hint)).with_span(span);
+
+                    for s in suggestions.iter() {
+                        error = error.with_suggestion(span, s, &format!("did you mean '{}'?", s))
+                    }
+
+                    Err(Box::new(error))
+                },
+            },
+            Expression::Block(ref statements, _) => {
+                let local_sym = Rc::new(SymTab::new(sym.clone(), &[]));
+
+                for s in statements {
+                    s.resolve(&local_sym)?
+                }
+
+                Ok(())
+            },
+            Expression::Arm(ref params, ref body, _) => {
+                let mut param_names = Vec::new();
+
+                for p in params {
+                    p.pattern_names(&mut param_names);
+                    p.resolve(sym)?
+                }
+
+                let local_sym = Rc::new(SymTab::new(sym.clone(), param_names.as_slice()));
+
+                body.resolve(&local_sym)
+            },
+            Expression::Param { default: Some(ref d), .. } => d.resolve(sym),
+            Expression::Assignment(ref l, ref r, _) |
+            Expression::Operation { left: ref l, right: ref r, .. } => {
+                l.resolve(sym)?;
+                r.resolve(sym)
+            },
+            Expression::Call(ref id, ref args, _) => {
+                id.resolve(sym)?;
+
+                for a in args.iter() {
+                    a.resolve(sym)?
+                }
+
+                Ok(())
+            },
+            Expression::Named(_, ref e, _) |
+            Expression::Field(ref e, _, _) => e.resolve(sym),
+            Expression::Record(ref fields, _) => {
+                for (_, e) in fields.iter() {
+                    e.resolve(sym)?
+                }
+
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
+    pub fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> ParserResult<()> {
+        match *self {
+            Expression::Block(ref statements, _) => {
+                for s in statements {
+                    s.visit(&sym, &env)?
+                }
+                Ok(())
+            },
+            Expression::Identifier(ref id, span) => match sym.get_name(id) {
+                Some(_) => Ok(()),
+                None    => Err(Box::new(Diagnostic::error("E0101", &format!("{}: undeclared", id)).with_span(span))),
+            },
+            Expression::Arm(ref params, ref body, _) => {
+                let mut param_names = Vec::new();
+
+                for p in params {
+                    p.pattern_names(&mut param_names)
+                }
+
+                let param_types = vec![Type::Any; param_names.len()];
+              
+                for p in params {
+                    if let Expression::Param { ref name, t: Some(ref t), default: Some(ref d), .. } = **p {
+                        let default = d.get_type(sym, env)?;
+
+                        if !t.compare(&default) {
+                            return Err(Box::new(Diagnostic::error("E0201", &format!("{}: expected default of '{:?}', got '{:?}'", name, t, default)).with_span(d.span())))
+                        }
+                    }
+                }
+
+                let local_sym = Rc::new(SymTab::new(sym.clone(), param_names.as_slice()));
+                let local_env = Rc::new(TypeTab::new(env.clone(), &param_types));
+
+                body.visit(&local_sym, &local_env)
+            },
+            Expression::Assignment(ref l, ref r, _) => {
+                l.visit(sym, env)?;
+                r.visit(sym, env)
+            },
+            Expression::Operation { ref left, ref right, .. } => {
+                left.visit(sym, env)?;
+                right.visit(sym, env)
+            },
+            Expression::Record(ref fields, _) => {
+                for (_, value) in fields.iter() {
+                    value.visit(sym, env)?
+                }
+                Ok(())
+            },
+            Expression::Field(ref object, _, _) => object.visit(sym, env),
+            _ => Ok(())
+        }
+    }
+    
+    pub fn lower(&self, sym: &Rc<SymTab>) -> ParserResult<Expression> {
+        match *self {
+            Expression::Block(ref statements, span) => {
+                let local_sym = Rc::new(SymTab::new(sym.clone(), &[]));
+
+                let mut lowered = Vec::new();
+
+                for s in statements {
+                    lowered.push(s.lower(&local_sym)?)
+                }
+
+                Ok(Expression::Block(lowered, span))
+            },
+            Expression::Arm(ref params, ref body, span) => {
+                let mut param_names = Vec::new();
+
+                for p in params {
+                    p.pattern_names(&mut param_names)
+                }
+
+                let local_sym = Rc::new(SymTab::new(sym.clone(), param_names.as_slice()));
+
+                Ok(Expression::Arm(params.clone(), Rc::new(body.lower(&local_sym)?), span))
+            },
+            Expression::Assignment(ref l, ref r, span) => Ok(Expression::Assignment(l.clone(), Rc::new(r.lower(sym)?), span)),
+            Expression::Operation { ref left, ref op, ref right, span } => Ok(
+                Expression::Operation {
+                    left:  Rc::new(left.lower(sym)?),
+                    op:    op.clone(),
+                    right: Rc::new(right.lower(sym)?),
+                    span,
+                }
+            ),
+            Expression::Named(ref name, ref value, span) => Ok(Expression::Named(name.clone(), Rc::new(value.lower(sym)?), span)),
+            Expression::Record(ref fields, span) => {
+                let mut lowered = Vec::new();
+
+                for (name, value) in fields.iter() {
+                    lowered.push((name.clone(), Rc::new(value.lower(sym)?)))
+                }
+
+                Ok(Expression::Record(lowered, span))
+            },
+            Expression::Field(ref object, ref name, span) => Ok(Expression::Field(Rc::new(object.lower(sym)?), name.clone(), span)),
+            Expression::Call(ref id, ref args, span) => {
+                let mut lowered = Vec::new();
+
+                for a in args.iter() {
+                    lowered.push(a.lower(sym)?)
+                }
+
+                let named = lowered.iter().any(|a| matches!(*a, Expression::Named(..)));
+
+                if named {
+                    lowered = match **id {
+                        Expression::Identifier(ref n, _) => match sym.get_arms(n) {
+                            Some(arms) => resolve_arguments(n, &arms, &lowered).map_err(|e| e.at(span))?,
+                            None       => return Err(Box::new(Diagnostic::error("E0210", &format!("{}: can't use named arguments, no known arms", n)).with_span(id.span()))),
+                        },
+                        _ => return Err(Box::new(Diagnostic::error("E0210", "can't use named arguments on anonymous call").with_span(id.span()))),
+                    }
+                }
+
+                Ok(Expression::Call(Rc::new(id.lower(sym)?), Rc::new(lowered), span))
+            },
+            _ => Ok(self.clone()),
+        }
+    }
+
+    pub fn lua(&self, f: &mut Emitter) -> fmt::Result {
+        match *self {
+            Expression::Block(ref statements, span) => {
+                writeln!(f, "function(...)")?;
+                f.indent();
+                writeln!(f, "local __args = {{...}}")?;
+
+                // the end of a block's source, which a bare return would stand for
+                let eof = |s: &Statement| match *s {
+                    Statement::Expression(ref e) => **e == Expression::EOF,
+                    _                            => false,
+                };
+
+                let last = statements.iter().rposition(|s| !eof(s));
+
+                let mut declared = Vec::new();
+
+                for (i, s) in statements.iter().enumerate() {
+                    if eof(s) {
+                        continue
+                    }
+
+                    f.statement(s.span())?;
+
+                    match *s {
+                        Statement::Expression(ref e) if Some(i) == last => e.lua_return(f)?,
+                        _                                               => s.lua_scoped(&mut declared, f)?,
+                    }
+
+                    writeln!(f)?;
+                }
+
+                f.dedent();
+                write!(f, "end")?;
+                f.mark(span);
+
+                Ok(())
+            }
+            Expression::Int(ref n, span) => {
+                // doubles hold integers exactly up to 2^53
+                if !f.options.target.has_integers() && n.unsigned_abs() > 1 << 53 {
+                    let message = format!("integer literal {} can't be represented exactly on {}", n, f.options.target);
+                    f.reject(span, "E0502", &message)
+                }
+
+                write!(f, "{}", n)
+            },
+            Expression::Float(ref n, _)        => write!(f, "{:?}", n),
+            Expression::Str(ref n, _)          => write!(f, r#""{}""#, n),
+            Expression::Bool(ref n, _)         => write!(f, "{}", n),
+            Expression::Nil(_)                 => write!(f, "nil"),
+            Expression::Identifier(ref n, _)   => write!(f, "{}", f.name(n)),
+            Expression::Param { ref name, .. } => write!(f, "{}", f.name(name)),
+            Expression::Assignment(ref a, ref b, _) => {
+                a.lua(f)?;
+                write!(f, " = ")?;
+                b.lua(f)
+            },
+            Expression::Call(ref id, ref args, _) => {
+                match **id {
+                    Expression::Field(ref object, ref name, _) => {
+                        Expression::lua_prefix(object, f)?;
+                        write!(f, ":{}", f.name(name))?
+                    },
+                    _ => id.lua(f)?,
+                }
+                write!(f, "(")?;
+
+                let mut acc = 1;
+                for e in args.iter() {
+                    e.lua(f)?;
+                    if acc != args.len() {
+                        write!(f, ",")?;
+                    }
+                    acc += 1;
+                }
+
+                write!(f, ")")
+            },
+            Expression::Record(ref fields, _) => {
+                write!(f, "{{")?;
+
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{} = ", f.name(name))?;
+                    value.lua(f)?;
+                }
+
+                write!(f, "}}")
+            },
+            Expression::Field(ref object, ref name, _) => {
+                Expression::lua_prefix(object, f)?;
+                write!(f, ".{}", f.name(name))
+            },
+            Expression::Arm(ref params, ref body, _) => {
+                let required = params.iter().filter(|p| !matches!(***p, Expression::Param { default: Some(_), .. })).count();
+
+                if required == params.len() {
+                    writeln!(f, r##"if {} == select("#", ...) then"##, params.len())?;
+                } else {
+                    writeln!(f, r##"if select("#", ...) >= {} and select("#", ...) <= {} then"##, required, params.len())?;
+                }
+
+                f.indent();
+
+                let mut acc  = 0usize;
+
+                for p in params {
+                    acc += 1;
+    
+                    match **p {
+                        Expression::Param { ref name, ref default, .. } => {
+                            let name = f.name(name);
+
+                            writeln!(f, "local {} = __args[{}]", name, acc)?;
+
+                            if let Some(ref d) = *default {
+                                write!(f, "if {} == nil then {} = ", name, name)?;
+                                d.lua(f)?;
+                                writeln!(f, " end")?
+                            }
+                        },
+                        _ => (),
+                    }
+                }
+                
+                let mut checks   = Vec::new();
+                let mut bindings = Vec::new();
+                let mut acc      = 0usize;
+
+                for p in params {
+                    acc += 1;
+
+                    match **p {
+                        Expression::Param { ref name, .. } => p.lua_pattern(&f.name(name), f, &mut checks, &mut bindings)?,
+                        ref c                              => c.lua_pattern(&format!("__args[{}]", acc), f, &mut checks, &mut bindings)?,
+                    }
+                }
+
+                if !checks.is_empty() {
+                    writeln!(f, "if {} then", checks.join(" and "))?;
+                    f.indent();
+                }
+
+                for b in bindings {
+                    writeln!(f, "{}", b)?;
+                }
+
+                f.mark(body.span());
+                body.lua_return(f)?;
+                writeln!(f)?;
+
+                if !checks.is_empty() {
+                    f.dedent();
+                    writeln!(f, "end")?;
+                }
+
+                f.dedent();
+                write!(f, "end")
+            },
+            Expression::Operation { ref left, op: Operand::Coalesce, ref right, .. } => {
+                write!(f, "(function(__v) if __v == nil then return ")?;
+                right.lua(f)?;
+                write!(f, " end return __v end)(")?;
+                left.lua(f)?;
+                write!(f, ")")
+            },
+            Expression::Operation { ref left, op: Operand::IDiv, ref right, .. } if !f.options.target.has_integers() => {
+                write!(f, "math.floor(")?;
+                left.lua(f)?;
+                write!(f, " / ")?;
+                right.lua(f)?;
+                write!(f, ")")
+            },
+            Expression::Operation { ref left, ref op, ref right, span } if op.bit_function().is_some() && !f.options.target.has_integers() => {
+                let library = match f.options.target.bit_library() {
+                    Some(l) => l,
+                    None    => {
+                        let message = format!("'{}' needs Lua 5.2 or newer, or LuaJIT", op);
+                        f.reject(span, "E0501", &message);
+                        "bit32"
+                    },
+                };
+
+                write!(f, "{}.{}(", library, op.bit_function().unwrap())?;
+                left.lua(f)?;
+                write!(f, ", ")?;
+                right.lua(f)?;
+                write!(f, ")")
+            },
+            Expression::Operation { ref left, ref op, ref right, .. } => {
+                write!(f, "(")?;
+                left.lua(f)?;
+                write!(f, " {} ", op)?;
+                right.lua(f)?;
+                write!(f, ")")
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Expression {
+    // returns the value from the enclosing function, a call is always emitted as
+    // `return f(...)` so Lua makes it a tail call and recursion can loop forever
+    fn lua_return(&self, f: &mut Emitter) -> fmt::Result {
+        match *self {
+            // arms return from inside their own checks
+            Expression::Arm(..) => self.lua(f),
+            Expression::Assignment(ref target, _, _) => {
+                self.lua(f)?;
+                writeln!(f)?;
+                write!(f, "return ")?;
+                target.lua(f)
+            },
+            _ => {
+                write!(f, "return ")?;
+                self.lua(f)
+            },
+        }
+    }
+
+    fn lua_prefix(object: &Expression, f: &mut Emitter) -> fmt::Result {
+        match *object {
+            Expression::Identifier(..) |
+            Expression::Field(..)      |
+            Expression::Call(..)      => object.lua(f),
+            _                         => {
+                write!(f, "(")?;
+                object.lua(f)?;
+                write!(f, ")")
+            },
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut emitter = Emitter::readable();
+        self.lua(&mut emitter)?;
+
+        f.write_str(&emitter.out)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Binding {
+    pub global:  bool,
+    pub mutable: bool,
+    pub span:    Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Definition(Binding, Option<Type>, Rc<String>, Option<Rc<Expression>>),
+    Expression(Rc<Expression>),
+    Data(Rc<DataType>),
+    Interface(Rc<Interface>),
+}
+
+#[allow(dead_code)]
+impl Statement {
+    pub fn span(&self) -> Span {
+        match *self {
+            Statement::Definition(binding, _, _, Some(ref e)) => binding.span.to(e.span()),
+            Statement::Definition(binding, ..)                => binding.span,
+            Statement::Expression(ref e)                      => e.span(),
+            Statement::Data(ref data)                         => data.span,
+            Statement::Interface(ref interface)               => interface.span,
+        }
+    }
+
+    // structural equality, ignoring where either side was written
+    pub fn same(&self, other: &Statement) -> bool {
+        match (self, other) {
+            (Statement::Definition(a, s, m, x), Statement::Definition(b, t, n, y)) => {
+                a.global == b.global && a.mutable == b.mutable && s == t && m == n && match (x, y) {
+                    (Some(x), Some(y)) => x.same(y),
+                    (None, None)       => true,
+                    _                  => false,
+                }
+            },
+            (Statement::Expression(a), Statement::Expression(b)) => a.same(b),
+            (Statement::Data(a), Statement::Data(b)) => {
+                a.name == b.name && a.params == b.params && a.variants == b.variants
+            },
+            (Statement::Interface(a), Statement::Interface(b)) => {
+                a.name == b.name && a.members == b.members
+            },
+            _ => false,
+        }
+    }
+
+    pub fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> ParserResult<()> {
+        match *self {
+            Statement::Expression(ref e) => {
+                e.get_type(sym, env)?;
+                e.visit(sym, env)
+            },
+            Statement::Data(ref data) => {
+                env.add_data(data.clone());
+
+                for (i, v) in data.variants.iter().enumerate() {
+                    let index = sym.add_name(&v.name);
+                    if index >= env.size() {
+                        env.grow();
+                    }
+
+                    env.set_type(index, 0, data.constructor(i)).unwrap();
+                }
+
+                Ok(())
+            },
+            Statement::Interface(ref interface) => {
+                let members = interface.members.iter().map(|(n, t)| (n.clone(), env.expand(t))).collect();
+
+                env.add_interface(Rc::new(Interface { name: interface.name.clone(), members, span: interface.span, }));
+
+                Ok(())
+            },
+            Statement::Definition(_, _, _, Some(_)) => {
+                let index = self.declare(sym, env).unwrap();
+
+                self.infer(index, sym, env)?;
+                self.settle(&[index], index, sym, env)
+            },
+            Statement::Definition(..) => Ok(()),
+        }
+    }
+
+    pub fn declare(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> Option<usize> {
+        match *self {
+            Statement::Definition(binding, ref t, ref id, Some(_)) => {
+                let index = sym.add_name(id);
+
+                if binding.mutable {
+                    sym.set_mutable(id)
+                }
+
+                if index >= env.size() {
+                    env.grow();
+                }
+
+                let var = match *t {
+                    Some(ref t) => env.expand(t),
+                    None        => env.fresh(),
+                };
+
+                env.set_type(index, 0, var).unwrap();
+
+                Some(index)
+            },
+            _ => None,
+        }
+    }
+
+    pub fn infer(&self, index: usize, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> ParserResult<()> {
+        if let Statement::Definition(_, ref t, ref id, Some(ref expr)) = *self {
+            let var        = env.get_type(index, 0).unwrap();
+            let right_hand = expr.get_type(sym, env)?;
+
+            if let Err(e) = env.unify_at(&var, &right_hand, expr.span()) {
+                let error = match *t {
+                    Some(_) => Diagnostic::error("E0201", &format!("{}: expected '{:?}', got '{:?}'", id, env.resolve(&var), env.resolve(&right_hand))).with_span(expr.span()),
+                    None    => Diagnostic::error("E0201", &format!("{}: {}", id, e)).with_span(expr.span()),
+                };
+
+                return Err(inferred(error, env.origin(&var), &env.resolve(&var)))
+            }
+
+            if t.is_none() {
+                env.set_type(index, 0, right_hand).unwrap()
+            }
+        }
+
+        Ok(())
+    }
+
+    // group holds every definition inferred together with this one
+    pub fn settle(&self, group: &[usize], index: usize, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> ParserResult<()> {
+        if let Statement::Definition(binding, _, _, Some(ref expr)) = *self {
+            let t = env.get_type(index, 0).unwrap();
+
+            // a mutable binding keeps one type for every later assignment
+            let t = if env.is_global() && !binding.mutable {
+                env.generalize(&t, group)
+            } else {
+                env.resolve(&t)
+            };
+
+            if let Err(e) = env.set_type(index, 0, t) {
+                return Err(Box::new(Diagnostic::error("E0201", &format!("error setting type: {}", e)).with_span(expr.span())))
+            }
+
+            expr.visit(sym, env)?
+        }
+
+        Ok(())
+    }
+
+    pub fn resolve(&self, sym: &Rc<SymTab>) -> ParserResult<()> {
+        match *self {
+            Statement::Expression(ref e) => e.resolve(sym),
+            Statement::Data(ref data) => {
+                for v in data.variants.iter() {
+                    sym.add_name(&v.name);
+                }
+
+                Ok(())
+            },
+            Statement::Definition(_, _, ref id, ref e) => {
+                sym.add_name(id);
+
+                match *e {
+                    Some(ref e) => e.resolve(sym),
+                    None        => Ok(()),
+                }
+            },
+            Statement::Interface(_) => Ok(()),
+        }
+    }
+
+    pub fn references(&self, deferred: bool, acc: &mut Vec<(Rc<String>, Span, bool)>) {
+        match *self {
+            Statement::Expression(ref e)            |
+            Statement::Definition(_, _, _, Some(ref e)) => e.references(deferred, acc),
+            _                                        => (),
+        }
+    }
+
+    pub fn declare_arms(&self, sym: &Rc<SymTab>) {
+        if let Statement::Definition(_, _, ref id, Some(ref e)) = *self {
+            if let Expression::Block(ref statements, _) = **e {
+                let mut arms = Vec::new();
+
+                for s in statements {
+                    if let Statement::Expression(ref a) = *s {
+                        if let Expression::Arm(ref params, _, _) = **a {
+                            arms.push(params.clone())
+                        }
+                    }
+                }
+
+                sym.add_name(id);
+                sym.set_arms(id, Rc::new(arms))
+            }
+        }
+    }
+    
+    pub fn lower(&self, sym: &Rc<SymTab>) -> ParserResult<Statement> {
+        match *self {
+            Statement::Expression(ref e) => Ok(Statement::Expression(Rc::new(e.lower(sym)?))),
+            Statement::Data(ref data) => {
+                for v in data.variants.iter() {
+                    sym.add_name(&v.name);
+                }
+
+                Ok(self.clone())
+            },
+            Statement::Interface(_) => Ok(self.clone()),
+            Statement::Definition(binding, ref t, ref id, ref e) => {
+                sym.add_name(id);
+                self.declare_arms(sym);
+
+                match *e {
+                    Some(ref e) => {
+                        Ok(Statement::Definition(binding, t.clone(), id.clone(), Some(Rc::new(e.lower(sym)?))))
+                    },
+                    None => Ok(self.clone()),
+                }
+            },
+        }
+    }
+
+    pub fn lua(&self, f: &mut Emitter) -> fmt::Result {
+        match *self {
+            Statement::Expression(ref e) => e.lua(f),
+            Statement::Data(ref data) => {
+                for (i, v) in data.variants.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?
+                    }
+
+                    if v.fields.is_empty() {
+                        write!(f, r#"{} = {{tag = "{}"}}"#, f.name(&v.name), v.name)?
+                    } else {
+                        write!(f, r#"{} = function(...) return {{tag = "{}", ...}} end"#, f.name(&v.name), v.name)?
+                    }
+                }
+
+                Ok(())
+            },
+            Statement::Interface(_) => Ok(()),
+            Statement::Definition(_, _, ref id, Some(ref e)) => {
+                write!(f, "{} = ", f.name(id))?;
+                e.lua(f)
+            },
+            Statement::Definition(..) => Ok(()),
+        }
+    }
+
+    // whether any Lua is written for the statement at all
+    pub fn emits(&self) -> bool {
+        match *self {
+            Statement::Data(ref data)            => !data.variants.is_empty(),
+            Statement::Interface(_)              |
+            Statement::Definition(_, _, _, None) => false,
+            _                                    => true,
+        }
+    }
+
+    // names this statement introduces into the enclosing scope
+    pub fn locals(&self) -> Vec<Rc<String>> {
+        match *self {
+            Statement::Definition(binding, _, ref id, _) if !binding.global => vec![id.clone()],
+            Statement::Data(ref data) => data.variants.iter().map(|v| v.name.clone()).collect(),
+            _                         => Vec::new(),
+        }
+    }
+
+    // emits the statement, declaring its names as locals the first time they appear
+    pub fn lua_scoped(&self, declared: &mut Vec<Rc<String>>, f: &mut Emitter) -> fmt::Result {
+        let fresh: Vec<Rc<String>> = self.locals().into_iter().filter(|n| !declared.contains(n)).collect();
+
+        if fresh.is_empty() {
+            return self.lua(f)
+        }
+
+        declared.extend(fresh.iter().cloned());
+
+        if let Statement::Definition(_, _, ref id, Some(ref e)) = *self {
+            let mut refs = Vec::new();
+            e.references(false, &mut refs);
+
+            if !refs.iter().any(|(n, _, _)| n == id) {
+                write!(f, "local ")?;
+                return self.lua(f)
+            }
+        }
+
+        let names: Vec<String> = fresh.iter().map(|n| f.name(n)).collect();
+
+        writeln!(f, "local {}", names.join(", "))?;
+        self.lua(f)
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut emitter = Emitter::readable();
+        self.lua(&mut emitter)?;
+
+        f.write_str(&emitter.out)
+    }
+}
+
+// top-level names are locals declared up front so blocks can refer to later
+// definitions, and are handed back as the chunk's module table
+pub struct Program<'a>(pub &'a [Statement]);
+
+impl<'a> Program<'a> {
+    // the Lua for a whole chunk with its source map, or whatever the target rejected
+    pub fn emit(&self, options: Options, file: &str) -> Result<(String, SourceMap), Vec<Diagnostic>> {
+        let mut f = Emitter::with_options(options, file);
+
+        // writing to a string can't fail
+        let _ = self.lua(&mut f);
+
+        if f.errors.is_empty() {
+            Ok((f.out, f.map))
+        } else {
+            Err(f.errors)
+        }
+    }
+
+    // statements spanning several lines are set apart by blank lines
+    pub fn lua(&self, f: &mut Emitter) -> fmt::Result {
+        let mut declared: Vec<Rc<String>> = Vec::new();
+
+        for s in self.0.iter() {
+            for n in s.locals() {
+                if !declared.contains(&n) {
+                    declared.push(n)
+                }
+            }
+        }
+
+        let names: Vec<String> = declared.iter().map(|n| f.name(n)).collect();
+
+        let mut spaced = true;
+
+        if !names.is_empty() {
+            writeln!(f, "local {}", names.join(", "))?;
+            spaced = false
+        }
+
+        for s in self.0.iter().filter(|s| s.emits()) {
+            let start = f.here();
+
+            f.statement(s.span())?;
+
+            let code = f.out.len();
+            s.lua(f)?;
+
+            let multiline = f.out[code ..].contains('\n');
+            writeln!(f)?;
+
+            if multiline {
+                if !spaced {
+                    f.blank_line_at(start)
+                }
+
+                writeln!(f)?
+            }
+
+            spaced = multiline
+        }
+
+        if !spaced {
+            writeln!(f)?
+        }
+
+        // modules are required from Lua, so they're keyed by the snail names
+        let exports: Vec<String> = declared.iter().zip(names.iter()).map(|(n, lua)| {
+            if **n == *lua {
+                format!("{} = {}", lua, lua)
+            } else {
+                format!(r#"["{}"] = {}"#, n, lua)
+            }
+        }).collect();
+
+        writeln!(f, "return {{{}}}", exports.join(", "))?;
+
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Program<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.emit(Options::default(), "") {
+            Ok((lua, _)) => f.write_str(&lua),
+            Err(_)       => Err(fmt::Error),
+        }
+    }
+}
+
+pub fn lower_program(statements: &[Statement], sym: &Rc<SymTab>) -> ParserResult<Vec<Statement>> {
+    for s in statements {
+        s.declare_arms(sym)
+    }
+
+    statements.iter().map(|s| s.lower(sym)).collect()
+}
+
+// every top-level name is declared up front, then definitions are inferred
+// one strongly connected group at a time, dependencies first. Failures are
+// reported as they're found and checking carries on as far as it safely can
+pub fn visit_program(statements: &[Statement], sym: &Rc<SymTab>, env: &Rc<TypeTab>, diagnostics: &Diagnostics) {
+    let mut names: Vec<Rc<String>> = Vec::new();
+    let mut slots: Vec<usize>      = Vec::new();
+    let mut nodes: Vec<Vec<usize>> = Vec::new();
+
+    for (i, s) in statements.iter().enumerate() {
+        match *s {
+            Statement::Data(_) | Statement::Interface(_) => if let Err(e) = s.visit(sym, env) {
+                diagnostics.report(*e)
+            },
+            Statement::Definition(_, _, ref id, Some(_)) => match names.iter().position(|n| n == id) {
+                Some(n) => nodes[n].push(i),
+                None    => {
+                    slots.push(s.declare(sym, env).unwrap());
+                    names.push(id.clone());
+                    nodes.push(vec![i])
+                },
+            },
+            _ => (),
+        }
+    }
+
+    for s in statements {
+        if let Err(e) = s.resolve(sym) {
+            diagnostics.report(*e)
+        }
+    }
+
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+
+    for (i, s) in statements.iter().enumerate() {
+        let mut refs = Vec::new();
+        s.references(false, &mut refs);
+
+        let from = match *s {
+            Statement::Definition(_, _, ref id, Some(_)) => names.iter().position(|n| n == id),
+            _                                         => None,
+        };
+
+        for (name, span, deferred) in refs {
+            if let Some(n) = names.iter().position(|m| *m == name) {
+                if !deferred && nodes[n][0] >= i {
+                    diagnostics.report(
+                        Diagnostic::error("E0102", &format!("{}: used before its definition", name))
+                            .with_span(span)
+                            .with_label(statements[nodes[n][0]].span(), "defined here")
+                            .with_note("only code inside a block may refer to later definitions")
+                    )
+                }
+
+                if let Some(from) = from {
+                    if !edges[from].contains(&n) {
+                        edges[from].push(n)
+                    }
+                }
+            }
+        }
+    }
+
+    // types can't be checked against names that don't resolve
+    if diagnostics.has_errors() {
+        return
+    }
+
+    for group in strongly_connected(&edges) {
+        let indices: Vec<usize> = group.iter().map(|&n| slots[n]).collect();
+        let mut failed          = Vec::new();
+
+        for &n in group.iter() {
+            for &i in nodes[n].iter() {
+                if let Err(e) = statements[i].infer(slots[n], sym, env) {
+                    diagnostics.report(*e);
+
+                    // a definition that failed fits anywhere, so it isn't reported again at every use
+                    env.set_type(slots[n], 0, Type::Any).unwrap();
+                    failed.push(i)
+                }
+            }
+        }
+
+        for &n in group.iter() {
+            for &i in nodes[n].iter().filter(|i| !failed.contains(i)) {
+                if let Err(e) = statements[i].settle(&indices, slots[n], sym, env) {
+                    diagnostics.report(*e)
+                }
+            }
+        }
+    }
+
+    for s in statements {
+        if let Statement::Expression(_) = *s {
+            if let Err(e) = s.visit(sym, env) {
+                diagnostics.report(*e)
+            }
+        }
+    }
+}
+
+// tarjan's algorithm; groups come out after every group they depend on
+fn strongly_connected(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        edges:   &'a [Vec<usize>],
+        index:   Vec<Option<usize>>,
+        low:     Vec<usize>,
+        stack:   Vec<usize>,
+        on:      Vec<bool>,
+        counter: usize,
+        groups:  Vec<Vec<usize>>,
+    }
+
+    fn connect(state: &mut State, v: usize) {
+        state.index[v] = Some(state.counter);
+        state.low[v]   = state.counter;
+        state.counter += 1;
+
+        state.stack.push(v);
+        state.on[v] = true;
+
+        for &w in state.edges[v].iter() {
+            match state.index[w] {
+                None => {
+                    connect(state, w);
+                    state.low[v] = state.low[v].min(state.low[w])
+                },
+                Some(i) if state.on[w] => state.low[v] = state.low[v].min(i),
+                _ => (),
+            }
+        }
+
+        if Some(state.low[v]) == state.index[v] {
+            let mut group = Vec::new();
+
+            loop {
+                let w = state.stack.pop().unwrap();
+                state.on[w] = false;
+                group.push(w);
+
+                if w == v {
+                    break
+                }
+            }
+
+            group.sort();
+            state.groups.push(group)
+        }
+    }
+
+    let mut state = State {
+        edges,
+        index:   vec![None; edges.len()],
+        low:     vec![0; edges.len()],
+        stack:   Vec::new(),
+        on:      vec![false; edges.len()],
+        counter: 0,
+        groups:  Vec::new(),
+    };
+
+    for v in 0 .. edges.len() {
+        if state.index[v].is_none() {
+            connect(&mut state, v)
+        }
+    }
+
+    state.groups
+}
+
+fn resolve_arguments(callee: &str, arms: &[Vec<Rc<Expression>>], args: &[Expression]) -> ParserResult<Vec<Expression>> {
+    let mut names: Vec<&Rc<String>> = Vec::new();
+
+    for a in args {
+        if let Expression::Named(ref n, _, span) = *a {
+            if names.contains(&n) {
+                return Err(Box::new(Diagnostic::error("E0209", &format!("{}: argument '{}' given more than once", callee, n)).with_span(span)))
+            }
+            names.push(n)
+        }
+    }
+
+    let mut resolved: Option<Vec<Expression>> = None;
+
+    'arms: for params in arms {
+        let mut slots: Vec<Option<Expression>> = vec![None; params.len()];
+        let mut position = 0;
+
+        for a in args {
+            match *a {
+                Expression::Named(..) => (),
+                ref e => {
+                    if position >= params.len() {
+                        continue 'arms
+                    }
+
+                    slots[position] = Some(e.clone());
+                    position += 1
+                },
+            }
+        }
+
+        for a in args {
+            if let Expression::Named(ref n, ref value, _) = *a {
+                let index = params.iter().position(|p| match **p {
+                    Expression::Param { ref name, .. } => name == n,
+                    _                                  => false,
+                });
+
+                match index {
+                    Some(i) if slots[i].is_none() => slots[i] = Some((**value).clone()),
+                    _                             => continue 'arms,
+                }
+            }
+        }
+
+        let mut lowered = Vec::new();
+
+        for (slot, p) in slots.into_iter().zip(params.iter()) {
+            match slot {
+                Some(e) => lowered.push(e),
+                None    => match **p {
+                    Expression::Param { default: Some(ref d), .. } => lowered.push((**d).clone()),
+                    _                                              => continue 'arms,
+                },
+            }
+        }
+
+        match resolved {
+            Some(ref r) if r.len() != lowered.len() || r.iter().zip(lowered.iter()).any(|(a, b)| !a.same(b)) => return Err(Box::new(Diagnostic::error("E0210", &format!("{}: named arguments are ambiguous between arms", callee)))),
+            Some(_) => (),
+            None    => resolved = Some(lowered),
+        }
+    }
+
+    match resolved {
+        Some(r) => Ok(r),
+        None    => Err(Box::new(Diagnostic::error("E0210", &format!("{}: no arm accepts the given arguments", callee)))),
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Type {
+    Str, Num, Int, Float, Bool, Any, Undefined,
+    Block(Rc<Signature>),
+    Overload(Rc<Vec<Signature>>),
+    List(Rc<Type>),
+    Option(Rc<Type>),
+    Union(Rc<Vec<Type>>),
+    Data(Rc<String>, Rc<Vec<Type>>),
+    Record(Rc<Vec<(Rc<String>, Type)>>),
+    Interface(Rc<Interface>),
+    Var(usize),
+    Generic(Rc<String>),
+}
+
+#[allow(unused)]
+impl Type {
+    pub fn compare(&self, other: &Type) -> bool {
+        match (self, other) {
+            // anything but nil, a possibly nil value has to be narrowed first
+            (Type::Any, Type::Option(_))          => false,
+            (Type::Any, _) | (_, Type::Any)       => true,
+            (Type::Var(_), _) | (_, Type::Var(_)) => true,
+            (Type::Num, Type::Int)   |
+            (Type::Num, Type::Float) |
+            (Type::Float, Type::Int) => true,
+            (Type::Union(a), Type::Union(b))   => b.iter().all(|b| a.iter().any(|a| a.compare(b))),
+            (Type::Union(a), b)                => a.iter().any(|a| a.compare(b)),
+            (Type::Block(a), Type::Block(b))   => a.compare(b),
+            (Type::List(a), Type::List(b))     => a.compare(b),
+            (Type::Option(a), Type::Option(b)) => a.compare(b),
+            (Type::Option(a), b)               => a.compare(b),
+            (Type::Data(a, x), Type::Data(b, y)) => {
+                a == b && x.len() == y.len() && x.iter().zip(y.iter()).all(|(x, y)| x.compare(y))
+            },
+            (Type::Record(a), Type::Record(b)) => a.iter().all(|(n, t)| {
+                b.iter().any(|(m, u)| n == m && t.compare(u))
+            }),
+            (Type::Interface(a), Type::Interface(b)) => a.name == b.name || a.members.iter().all(|(n, t)| {
+                b.members.iter().any(|(m, u)| n == m && t.compare(u))
+            }),
+            (Type::Interface(a), Type::Record(b)) => a.check(b).is_ok(),
+            (a, b)                                => a == b,
+        }
+    }
+
+    pub fn union(types: Vec<Type>) -> Type {
+        let mut members: Vec<Type> = Vec::new();
+
+        for t in types {
+            let flat = match t {
+                Type::Union(ref ts) => (**ts).clone(),
+                t                   => vec![t],
+            };
+
+            for t in flat {
+                if t.is_number() {
+                    if members.iter().any(|m| m.is_number() && m.compare(&t)) {
+                        continue
                     }
-                    acc += 1
+
+                    members.retain(|m| !(m.is_number() && t.compare(m)))
                 }
-                
-                Err(ParserError::new(&format!("missing return value")))
-            }
-            Expression::Arm(ref params, ref body) => {
-                let mut param_names = Vec::new();
-                let mut param_types = Vec::new();
 
-                for p in params {
-                    match **p {
-                        Expression::Identifier(ref n) => {
-                            param_types.push(Type::Any);
-                            param_names.push(n.clone())
-                        },
-                        _ => (),
-                    }
+                if !members.contains(&t) {
+                    members.push(t)
                 }
+            }
+        }
 
-                let local_sym = Rc::new(SymTab::new(sym.clone(), param_names.as_slice()));
-                let local_env = Rc::new(TypeTab::new(env.clone(), &param_types));
+        if members.len() == 1 {
+            members.pop().unwrap()
+        } else {
+            Type::Union(Rc::new(members))
+        }
+    }
 
-                body.get_type(&local_sym, &local_env)
-            }
-            _ => Ok(Type::Undefined),
+    pub fn members(&self) -> Vec<Type> {
+        match *self {
+            Type::Union(ref ts) => (**ts).clone(),
+            ref t               => vec![t.clone()],
         }
     }
 
-    pub fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> ParserResult<()> {
+    pub fn is_number(&self) -> bool {
+        matches!(*self, Type::Num | Type::Int | Type::Float)
+    }
+
+    pub fn widen(self) -> Type {
+        match self {
+            Type::Int | Type::Float => Type::Num,
+            t                       => t,
+        }
+    }
+
+    pub fn or_any(self) -> Type {
+        match self {
+            Type::Var(_) => Type::Any,
+            t            => t,
+        }
+    }
+
+    pub fn has_vars(&self) -> bool {
         match *self {
-            Expression::Block(ref statements) => {
-                for s in statements {
-                    s.visit(&sym, &env)?
+            Type::Var(_) | Type::Generic(_) => true,
+            Type::Block(ref sig)            => sig.params.iter().any(|p| p.has_vars()) || sig.ret.has_vars(),
+            Type::Overload(ref s)           => s.iter().any(|sig| Type::Block(Rc::new(sig.clone())).has_vars()),
+            Type::List(ref t)               |
+            Type::Option(ref t)             => t.has_vars(),
+            Type::Data(_, ref a)            |
+            Type::Union(ref a)              => a.iter().any(|t| t.has_vars()),
+            Type::Record(ref fields)        => fields.iter().any(|(_, t)| t.has_vars()),
+            _                               => false,
+        }
+    }
+
+    pub fn occurs(&self, var: usize) -> bool {
+        match *self {
+            Type::Var(v)          => v == var,
+            Type::Block(ref sig)  => sig.occurs(var),
+            Type::Overload(ref s) => s.iter().any(|sig| sig.occurs(var)),
+            Type::List(ref t)     |
+            Type::Option(ref t)   => t.occurs(var),
+            Type::Data(_, ref a)  |
+            Type::Union(ref a)    => a.iter().any(|t| t.occurs(var)),
+            Type::Record(ref f)   => f.iter().any(|(_, t)| t.occurs(var)),
+            _                     => false,
+        }
+    }
+
+    pub fn lua_name(&self) -> Option<&'static str> {
+        match *self {
+            Type::Str      => Some("string"),
+            Type::Num      |
+            Type::Int      |
+            Type::Float    => Some("number"),
+            Type::Bool     => Some("boolean"),
+            Type::Block(_)    |
+            Type::Overload(_) => Some("function"),
+            Type::List(_)      |
+            Type::Data(..)     |
+            Type::Record(_)    |
+            Type::Interface(_) => Some("table"),
+            _                 => None,
+        }
+    }
+
+    pub fn member(&self, name: &str) -> Result<Type, String> {
+        let found = match *self {
+            Type::Any | Type::Var(_) => return Ok(Type::Any),
+            Type::Option(_)          => return Err(format!("can't access '{}', value is possibly nil", name)),
+            Type::Record(ref fields) => fields.iter().find(|&(n, _)| **n == name).map(|(_, t)| t.clone()),
+            Type::Interface(ref i)   => i.members.iter().find(|&(n, _)| **n == name).map(|(_, t)| t.clone()),
+            _                        => None,
+        };
+
+        found.ok_or_else(|| format!("'{:?}' has no member '{}'", self, name))
+    }
+
+    // record members are called with the record as their first argument
+    pub fn method(self) -> Result<Type, String> {
+        let strip = |sig: &Signature| if sig.params.is_empty() && !sig.variadic {
+            Err(format!("method must take self as its first parameter, got '{:?}'", sig))
+        } else {
+            Ok(Signature::new(sig.params.iter().skip(1).cloned().collect(), sig.required.saturating_sub(1), sig.variadic, sig.ret.clone()))
+        };
+
+        match self {
+            Type::Block(ref sig)     => Ok(Type::Block(Rc::new(strip(sig)?))),
+            Type::Overload(ref sigs) => Ok(Type::Overload(Rc::new(sigs.iter().map(strip).collect::<Result<_, _>>()?))),
+            t                        => Ok(t),
+        }
+    }
+
+    // without an integer subtype any whole number passes for an int
+    pub fn lua_check(&self, path: &str, target: Target) -> Option<String> {
+        match *self {
+            Type::Int if target.has_integers() => Some(format!(r#"math.type({}) == "integer""#, path)),
+            Type::Int => Some(format!(r#"(type({}) == "number" and {} % 1 == 0)"#, path, path)),
+            ref t     => t.lua_name().map(|n| format!(r#"type({}) == "{}""#, path, n)),
+        }
+    }
+}
+
+impl fmt::Debug for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Type::Str              => write!(f, "Str"),
+            Type::Num              => write!(f, "Num"),
+            Type::Int              => write!(f, "Int"),
+            Type::Float            => write!(f, "Float"),
+            Type::Bool             => write!(f, "Bool"),
+            Type::Any              => write!(f, "Any"),
+            Type::Undefined        => write!(f, "Undefined"),
+            Type::Block(ref sig)   => write!(f, "Block({:?})", sig),
+            Type::Overload(ref s)  => write!(f, "Overload({:?})", s),
+            Type::List(ref t)      => write!(f, "[{:?}]", t),
+            Type::Option(ref t)    => write!(f, "{:?}?", t),
+            Type::Union(ref ts)    => {
+                for (i, t) in ts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " | ")?;
+                    }
+
+                    write!(f, "{:?}", t)?;
                 }
+
                 Ok(())
             },
-            Expression::Identifier(ref id) => match sym.get_name(&*id) {
-                Some(_) => Ok(()),
-                None    => Err(ParserError::new(&format!("{}: undeclared", id))),
+            Type::Data(ref n, ref args) => {
+                write!(f, "{}", n)?;
+
+                for a in args.iter() {
+                    write!(f, " {:?}", a)?;
+                }
+
+                Ok(())
             },
-            Expression::Arm(ref params, ref body) => {
-                let mut param_names = Vec::new();
-                let mut param_types = Vec::new();
+            Type::Record(ref fields) => {
+                write!(f, "[")?;
 
-                for p in params {
-                    match **p {
-                        Expression::Identifier(ref n) => {
-                            param_types.push(Type::Any);
-                            param_names.push(n.clone())
-                        },
-                        _ => (),
+                for (i, (n, t)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
                     }
+
+                    write!(f, "{}: {:?}", n, t)?;
                 }
-              
-                let local_sym = Rc::new(SymTab::new(sym.clone(), param_names.as_slice()));
-                let local_env = Rc::new(TypeTab::new(env.clone(), &param_types));
 
-                body.visit(&local_sym, &local_env)
+                write!(f, "]")
             },
-            Expression::Assignment(_, ref r) => { r.visit(&sym, &env) },
-            Expression::Operation {ref left, ref op, ref right} => {
-                left.visit(&sym, &env)?;
-                right.visit(&sym, &env)
-            },
-            _ => Ok(())
+            Type::Interface(ref i) => write!(f, "{}", i.name),
+            Type::Var(v)           => write!(f, "?{}", v),
+            Type::Generic(ref g)   => write!(f, "{}", g),
         }
     }
-    
-    pub fn lua(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Expression::Block(ref statements) => {
-                writeln!(f, "function(...) local __args = {{...}}")?;
-                let mut acc = 1;
-                for s in statements {
-                    if acc == statements.len() {
-                        match *s {
-                            Statement::Expression(ref e) => match **e {
-                                Expression::Arm(_, _) => (),
-                                _ => write!(f, "return ")?,
-                            },
-                            _ => (),
-                        }
-                    }
+}
 
-                    acc += 1;
-                    s.lua(f)?;
-                    writeln!(f)?;
-                }
-                writeln!(f, "end")
-            }
-            Expression::Number(ref n)     => write!(f, "{}", n),
-            Expression::Str(ref n)        => write!(f, r#""{}""#, n),
-            Expression::Bool(ref n)       => write!(f, "{}", n),
-            Expression::Identifier(ref n) => {
-                match n.as_str() {
-                    "while"  |
-                    "if"     |
-                    "else"   |
-                    "elseif" |
-                    "do"     |
-                    "local"  |
-                    "end"    |
-                    "then" => write!(f, "_{}", n),
-                    _      => write!(f, "{}", n),
-                }
-            },
-            Expression::Assignment(ref a, ref b) => write!(f, "{} = {}", a, b),
-            Expression::Call(ref id, ref args) => {
-                write!(f, "{}", id)?;
-                write!(f, "(")?;
+#[derive(Clone)]
+pub struct Signature {
+    pub params:   Vec<Type>,
+    pub required: usize,
+    pub variadic: bool,
+    pub ret:      Type,
+    // where inference fixed each parameter's type, if it did
+    pub origins:  Vec<Option<Span>>,
+}
 
-                let mut acc = 1;
-                for e in args.iter() {
-                    write!(f, "{}", e)?;
-                    if acc != args.len() {
-                        write!(f, ",")?;
-                    }
-                    acc += 1;
-                }
+// origins only explain a signature, they don't make it a different one
+impl PartialEq for Signature {
+    fn eq(&self, other: &Signature) -> bool {
+        self.params == other.params && self.required == other.required && self.variadic == other.variadic && self.ret == other.ret
+    }
+}
 
-                write!(f, ")")
-            },
-            Expression::Arm(ref params, ref body) => {
-                writeln!(f, "if {} == #__args then", params.len())?;
-                
-                let mut acc  = 0usize;
+impl Signature {
+    pub fn new(params: Vec<Type>, required: usize, variadic: bool, ret: Type) -> Signature {
+        Signature {
+            params,
+            required,
+            variadic,
+            ret,
+            origins: Vec::new(),
+        }
+    }
 
-                for p in params {
-                    acc += 1;
-    
-                    match **p {
-                        ref c @ Expression::Identifier(_) => writeln!(f, "local {} = __args[{}]", c, acc)?,
-                        _ => (),
+    pub fn with_origins(mut self, origins: Vec<Option<Span>>) -> Signature {
+        self.origins = origins;
+        self
+    }
+
+    pub fn origin(&self, param: usize) -> Option<Span> {
+        self.origins.get(param).cloned().unwrap_or(None)
+    }
+
+    // failures name the argument they're about when there is one
+    pub fn check(&self, args: &[Type]) -> Result<(), (Option<usize>, String)> {
+        if args.len() < self.required || (!self.variadic && args.len() > self.params.len()) {
+            let expected = if self.variadic {
+                format!("at least {}", self.required)
+            } else if self.required == self.params.len() {
+                format!("{}", self.required)
+            } else {
+                format!("{} to {}", self.required, self.params.len())
+            };
+
+            return Err((None, format!("expected {} argument(s), got {}", expected, args.len())))
+        }
+
+        for (i, (p, a)) in self.params.iter().zip(args.iter()).enumerate() {
+            if !p.compare(a) {
+                if let (Type::Interface(interface), Type::Record(fields)) = (p, a) {
+                    if let Err(e) = interface.check(fields) {
+                        return Err((Some(i), format!("argument {} doesn't satisfy '{}': {}", i + 1, interface.name, e)))
                     }
                 }
-                
-                let mut acc  = 0usize;
-                let mut flag = true;
 
-                for p in params {
-                    acc += 1;
-                    match **p {
-                        ref c @ Expression::Number(_) |
-                        ref c @ Expression::Bool(_) |
-                        ref c @ Expression::Operation { .. } |
-                        ref c @ Expression::Str(_) => {
-                            flag = false;
-
-                            writeln!(f, "if {} == __args[{}] then", c, acc)?;
-                            match **body {
-                                Expression::Block(_) => (),
-                                _ => write!(f, "return ")?
-                            }
-                            writeln!(f, "{}", body)?;
-                            writeln!(f, "end")?;
-                            continue
-                        }
-                        
-                        _ => (),
+                if let Type::Option(ref inner) = *a {
+                    if p.compare(inner) {
+                        return Err((Some(i), format!("argument {} is possibly nil, expected '{:?}'", i + 1, p)))
                     }
                 }
-                
-                if flag {
-                    writeln!(f, "return {}", body)?;
-                }
 
-                writeln!(f, "end")
-            },
-            Expression::Operation {ref left, ref op, ref right,} => {
-                write!(f, "(")?;
-                write!(f, "{}", left)?;
-                write!(f, " {} ", op)?;
-                write!(f, "{}", right)?;
-                write!(f, ")")
-            },
-            _ => Ok(()),
+                return Err((Some(i), format!("argument {} expected '{:?}', got '{:?}'", i + 1, p, a)))
+            }
         }
+
+        Ok(())
+    }
+
+    pub fn occurs(&self, var: usize) -> bool {
+        self.params.iter().any(|p| p.occurs(var)) || self.ret.occurs(var)
+    }
+
+    pub fn compare(&self, other: &Signature) -> bool {
+        self.params.len() == other.params.len()
+            && self.required == other.required
+            && self.variadic == other.variadic
+            && self.params.iter().zip(other.params.iter()).all(|(a, b)| a.compare(b))
+            && self.ret.compare(&other.ret)
     }
 }
 
-impl fmt::Display for Expression {
+impl fmt::Debug for Signature {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.lua(f)
+        write!(f, "(")?;
+
+        for (i, p) in self.params.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+
+            write!(f, "{:?}", p)?;
+
+            if i >= self.required {
+                write!(f, "?")?;
+            }
+        }
+
+        if self.variadic {
+            write!(f, "...")?;
+        }
+
+        write!(f, ") -> {:?}", self.ret)
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Statement {
-    Definition(Option<Type>, Rc<String>, Option<Rc<Expression>>),
-    Expression(Rc<Expression>),
+pub struct Variant {
+    pub name:   Rc<String>,
+    pub fields: Vec<Type>,
 }
 
-#[allow(dead_code)]
-impl Statement {
-    pub fn visit(&self, sym: &Rc<SymTab>, env: &Rc<TypeTab>) -> ParserResult<()> {
-        match *self {
-            Statement::Expression(ref e) => e.visit(sym, env),
-            Statement::Definition(ref t, ref id, ref e) => {
-                if let &Some(ref expr) = e {
-                    let index = sym.add_name(&id);
-                    if index >= env.size() {
-                        env.grow();
-                    }
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataType {
+    pub name:     Rc<String>,
+    pub params:   Vec<Rc<String>>,
+    pub variants: Vec<Variant>,
+    pub span:     Span,
+}
 
-                    let tp = match *t {
-                        Some(ref tt) => {
-                            let right_hand = &expr.get_type(sym, env)?;
-                            if !tt.compare(right_hand) {
-                                return Err(ParserError::new(&format!("{}: expected '{:?}', got '{:?}'", id, tt, right_hand)))
-                            }
-                            tt.clone()
-                        },
-                        None => expr.get_type(sym, env)?,
-                    };
+impl DataType {
+    pub fn constructor(&self, index: usize) -> Type {
+        let t = Type::Data(
+            self.name.clone(),
+            Rc::new(self.params.iter().map(|p| Type::Generic(p.clone())).collect())
+        );
 
-                    if let Err(e) = env.set_type(index, 0, tp) {
-                        Err(ParserError::new(&format!("error setting type: {}", e)))
-                    } else {
-                        expr.visit(sym, env)?;
-                        Ok(())
-                    }
-                } else {
-                    Ok(())
-                }
-            },
+        let fields = &self.variants[index].fields;
+
+        if fields.is_empty() {
+            t
+        } else {
+            Type::Block(Rc::new(Signature::new(fields.clone(), fields.len(), false, t)))
         }
     }
-    
-    pub fn lua(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Statement::Expression(ref e) => write!(f, "{}", e),
-            Statement::Definition(_, ref id, ref e) => {
-                let id = match id.as_str() {
-                    "while"  |
-                    "if"     |
-                    "else"   |
-                    "elseif" |
-                    "do"     |
-                    "local"  |
-                    "end"    |
-                    "then" => format!("_{}", id),
-                    _      => format!("{}", id),
-                };
+}
 
-                match *e {
-                    Some(ref e) => writeln!(f, "{} = {}", id, e),
-                    None        => writeln!(f, "local {}", id),
-                }
-            },
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interface {
+    pub name:    Rc<String>,
+    pub members: Vec<(Rc<String>, Type)>,
+    pub span:    Span,
+}
+
+impl Interface {
+    pub fn check(&self, fields: &[(Rc<String>, Type)]) -> Result<(), String> {
+        for (name, t) in self.members.iter() {
+            let found = match fields.iter().find(|&(n, _)| n == name) {
+                Some((_, found)) => found,
+                None             => return Err(format!("missing member '{}'", name)),
+            };
+
+            let expected = match *t {
+                Type::Block(ref sig) => {
+                    let mut params = vec![Type::Any];
+                    params.extend(sig.params.iter().cloned());
+
+                    Signature::new(params, sig.required + 1, sig.variadic, sig.ret.clone())
+                },
+                ref t => if t.compare(found) {
+                    continue
+                } else {
+                    return Err(format!("member '{}' expected '{:?}', got '{:?}'", name, t, found))
+                },
+            };
+
+            let ok = match *found {
+                Type::Block(ref sig)     => expected.compare(sig),
+                Type::Overload(ref sigs) => sigs.iter().any(|s| expected.compare(s)),
+                Type::Any | Type::Var(_) => true,
+                _                        => false,
+            };
+
+            if !ok {
+                return Err(format!("member '{}' expected method '{:?}', got '{:?}'", name, t, found))
+            }
         }
+
+        Ok(())
     }
 }
 
-impl fmt::Display for Statement {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.lua(f)
+// messages name what they're about when it has a name, the span shows the rest
+fn about(e: &Expression, message: &str) -> String {
+    match e.subject() {
+        Some(s) => format!("{}: {}", s, message),
+        None    => message.to_owned(),
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Type {
-    Str, Num, Bool, Any, Undefined, Block(Rc<Type>),
+// labels where a clashing type was inferred, when inference was what fixed it
+fn inferred(error: Diagnostic, origin: Option<Span>, t: &Type) -> Box<Diagnostic> {
+    match origin {
+        Some(span) => Box::new(error.with_label(span, &format!("'{:?}' inferred here", t))),
+        None       => Box::new(error),
+    }
 }
 
-#[allow(unused)]
-impl Type {
-    pub fn compare(&self, other: &Type) -> bool {
-        if self == &Type::Any || other == &Type::Any {
-            true
-        } else {
-            self == other
+fn check_exhaustive(arms: &[&Vec<Rc<Expression>>], env: &Rc<TypeTab>) -> ParserResult<()> {
+    let mut arities: Vec<usize> = arms.iter().map(|a| a.len()).collect();
+    arities.sort();
+    arities.dedup();
+
+    for arity in arities {
+        let group: Vec<&&Vec<Rc<Expression>>> = arms.iter().filter(|a| a.len() == arity).collect();
+
+        if group.iter().any(|a| a.iter().all(|p| p.is_irrefutable())) {
+            continue
+        }
+
+        for position in 0 .. arity {
+            let mut data    = None;
+            let mut covered = Vec::new();
+
+            for params in group.iter() {
+                if let Expression::Variant(ref name, ref fields, _) = *params[position] {
+                    if data.is_none() {
+                        data = env.get_variant(name).map(|(d, _)| d);
+                    }
+
+                    let rest = params.iter().enumerate().all(|(i, p)| i == position || p.is_irrefutable());
+
+                    if rest && fields.iter().all(|f| f.is_irrefutable()) {
+                        covered.push(name.clone())
+                    }
+                }
+            }
+
+            if let Some(data) = data {
+                let missing: Vec<String> = data.variants.iter()
+                    .filter(|v| !covered.contains(&v.name))
+                    .map(|v| format!("{}", v.name))
+                    .collect();
+
+                if !missing.is_empty() {
+                    return Err(Box::new(Diagnostic::error("E0207", &format!("non-exhaustive arms over {}: missing {}", data.name, missing.join(", ")))))
+                }
+            }
         }
     }
+
+    Ok(())
 }
 
 pub fn get_type(v: &str) -> Option<Type> {
     match v {
         "str"  => Some(Type::Str),
-        "num"  => Some(Type::Num),
+        "num"   => Some(Type::Num),
+        "int"   => Some(Type::Int),
+        "float" => Some(Type::Float),
         "bool" => Some(Type::Bool),
         "idc"  => Some(Type::Any),
         _      => None,
@@ -345,16 +2127,58 @@ pub fn get_type(v: &str) -> Option<Type> {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Operand {
     Pow,
-    Mul, Div, Mod,
+    Mul, Div, IDiv, Mod,
     Add, Sub,
+    BAnd, BOr, BXor, Shl, Shr,
     Equal, NEqual,
     Lt, Gt, LtEqual, GtEqual,
     And, Or, Not,
     Append,
+    Coalesce,
 }
 
 impl Operand {
-    pub fn operate(&self, lr: (Type, Type)) -> ParserResult<Type> {
+    pub fn is_numeric(&self) -> bool {
+        match *self {
+            Operand::Pow | Operand::Mul | Operand::Div | Operand::IDiv | Operand::Mod |
+            Operand::Add | Operand::Sub |
+            Operand::Lt  | Operand::Gt  | Operand::LtEqual | Operand::GtEqual => true,
+            ref op => op.is_integral(),
+        }
+    }
+
+    pub fn is_integral(&self) -> bool {
+        matches!(*self, Operand::BAnd | Operand::BOr | Operand::BXor | Operand::Shl | Operand::Shr)
+    }
+
+    pub fn operate(&self, lr: (Type, Type)) -> Result<Type, String> {
+        if self.is_integral() {
+            return match lr {
+                (Type::Int, Type::Int) => Ok(Type::Int),
+                (Type::Any, Type::Int) |
+                (Type::Int, Type::Any) |
+                (Type::Any, Type::Any) => Ok(Type::Any),
+                (a, b)                 => Err(format!("failed to '{:?} {} {:?}': bitwise operands must be Int", a, self, b)),
+            }
+        }
+
+        if lr.0.is_number() && lr.1.is_number() {
+            let joined = match lr {
+                (Type::Num, _) | (_, Type::Num)     => Type::Num,
+                (Type::Float, _) | (_, Type::Float) => Type::Float,
+                _                                   => Type::Int,
+            };
+
+            match *self {
+                Operand::Mul | Operand::IDiv | Operand::Mod |
+                Operand::Add | Operand::Sub  => return Ok(joined),
+                Operand::Div | Operand::Pow  => return Ok(if joined == Type::Num { Type::Num } else { Type::Float }),
+                _                            => (),
+            }
+        }
+
+        let lr = (lr.0.widen(), lr.1.widen());
+
         match *self {
             Operand::Pow => match lr {
                 (Type::Num, Type::Num) => Ok(Type::Num),
@@ -363,7 +2187,7 @@ impl Operand {
                 (Type::Str, Type::Num) => Ok(Type::Str),
                 (Type::Str, Type::Any) => Ok(Type::Any),
                 (Type::Any, Type::Any) => Ok(Type::Any),
-                (a, b)                 => Err(ParserError::new(&format!("failed to pow: {:?} and {:?}", a, b))),
+                (a, b)                 => Err(format!("failed to pow: {:?} and {:?}", a, b)),
             },
 
             Operand::Mul => match lr {
@@ -373,7 +2197,7 @@ impl Operand {
                 (Type::Str, Type::Num)  => Ok(Type::Str),
                 (Type::Str, Type::Str)  => Ok(Type::Str),
                 (Type::Any, Type::Any)  => Ok(Type::Any),
-                (a, b)                  => Err(ParserError::new(&format!("failed to multiply: {:?} and {:?}", a, b))),
+                (a, b)                  => Err(format!("failed to multiply: {:?} and {:?}", a, b)),
             },
 
             Operand::Div => match lr {
@@ -381,7 +2205,15 @@ impl Operand {
                 (Type::Any, Type::Num)  => Ok(Type::Any),
                 (Type::Num, Type::Any)  => Ok(Type::Any),
                 (Type::Any, Type::Any)  => Ok(Type::Any),
-                (a, b)                  => Err(ParserError::new(&format!("failed to divide: {:?} and {:?}", a, b))),
+                (a, b)                  => Err(format!("failed to divide: {:?} and {:?}", a, b)),
+            },
+
+            Operand::IDiv => match lr {
+                (Type::Num, Type::Num)  => Ok(Type::Num),
+                (Type::Any, Type::Num)  => Ok(Type::Any),
+                (Type::Num, Type::Any)  => Ok(Type::Any),
+                (Type::Any, Type::Any)  => Ok(Type::Any),
+                (a, b)                  => Err(format!("failed to floor divide: {:?} and {:?}", a, b)),
             },
 
             Operand::Mod => match lr {
@@ -389,7 +2221,7 @@ impl Operand {
                 (Type::Any, Type::Num)  => Ok(Type::Any),
                 (Type::Num, Type::Any)  => Ok(Type::Any),
                 (Type::Any, Type::Any)  => Ok(Type::Any),
-                (a, b)                  => Err(ParserError::new(&format!("failed to mod: {:?} and {:?}", a, b))),
+                (a, b)                  => Err(format!("failed to mod: {:?} and {:?}", a, b)),
             },
 
             Operand::Add => match lr {
@@ -400,7 +2232,7 @@ impl Operand {
                 (Type::Str, Type::Str)  => Ok(Type::Str),
                 (Type::Str, Type::Bool) => Ok(Type::Str),
                 (Type::Any, Type::Any)  => Ok(Type::Any),
-                (a, b)                  => Err(ParserError::new(&format!("failed to add: {:?} and {:?}", a, b))),
+                (a, b)                  => Err(format!("failed to add: {:?} and {:?}", a, b)),
             },
 
             Operand::Sub => match lr {
@@ -410,7 +2242,7 @@ impl Operand {
                 (Type::Str, Type::Num)  => Ok(Type::Str),
                 (Type::Str, Type::Str)  => Ok(Type::Str),
                 (Type::Any, Type::Any)  => Ok(Type::Any),
-                (a, b)                  => Err(ParserError::new(&format!("failed to subtract: {:?} and {:?}", a, b))),
+                (a, b)                  => Err(format!("failed to subtract: {:?} and {:?}", a, b)),
             },
             
             Operand::Append => match lr {
@@ -420,20 +2252,27 @@ impl Operand {
                 (Type::Str, _)          => Ok(Type::Str),
                 (Type::Any, Type::Str)  => Ok(Type::Str),
                 (Type::Any, Type::Any)  => Ok(Type::Any),
-                (a, b)                  => Err(ParserError::new(&format!("failed to append: {:?} and {:?}", a, b))),
+                (a, b)                  => Err(format!("failed to append: {:?} and {:?}", a, b)),
             },
 
             Operand::Equal | Operand::NEqual => Ok(Type::Bool),
 
             Operand::Lt | Operand::Gt | Operand::LtEqual | Operand::GtEqual => match lr {
-                (a @ Type::Bool, b @ _) => Err(ParserError::new(&format!("failed to '{:?} < {:?}'", a, b))),
-                (a @ _, b @ Type::Bool) => Err(ParserError::new(&format!("failed to '{:?} < {:?}'", a, b))),
-                (a @ Type::Str, b @ _)  => Err(ParserError::new(&format!("failed to '{:?} < {:?}'", a, b))),
-                (a @ _, b @ Type::Str)  => Err(ParserError::new(&format!("failed to '{:?} < {:?}'", a, b))),
-                _                       => Ok(Type::Bool),
+                (a @ Type::Bool, b) => Err(format!("failed to '{:?} < {:?}'", a, b)),
+                (a, b @ Type::Bool) => Err(format!("failed to '{:?} < {:?}'", a, b)),
+                (a @ Type::Str, b)  => Err(format!("failed to '{:?} < {:?}'", a, b)),
+                (a, b @ Type::Str)  => Err(format!("failed to '{:?} < {:?}'", a, b)),
+                _                   => Ok(Type::Bool),
             },
 
             Operand::And | Operand::Or | Operand::Not => Ok(Type::Bool),
+
+            Operand::BAnd | Operand::BOr | Operand::BXor | Operand::Shl | Operand::Shr => unreachable!(),
+
+            Operand::Coalesce => match lr {
+                (Type::Option(t), _) => Ok((*t).clone()),
+                (t, _)               => Ok(t),
+            },
         }
     }
 
@@ -442,9 +2281,15 @@ impl Operand {
             Operand::Pow     => write!(f, "^"),
             Operand::Mul     => write!(f, "*"),
             Operand::Div     => write!(f, "/"),
+            Operand::IDiv    => write!(f, "//"),
             Operand::Mod     => write!(f, "%"),
             Operand::Add     => write!(f, "+"),
             Operand::Sub     => write!(f, "-"),
+            Operand::BAnd    => write!(f, "&"),
+            Operand::BOr     => write!(f, "|"),
+            Operand::BXor    => write!(f, "~"),
+            Operand::Shl     => write!(f, "<<"),
+            Operand::Shr     => write!(f, ">>"),
             Operand::Equal   => write!(f, "=="),
             Operand::NEqual  => write!(f, "~="),
             Operand::Lt      => write!(f, "<"),
@@ -455,6 +2300,21 @@ impl Operand {
             Operand::Or      => write!(f, "or"),
             Operand::Not     => write!(f, "not"),
             Operand::Append  => write!(f, ".."),
+            Operand::Coalesce => write!(f, "?"),
+        }
+    }
+}
+
+impl Operand {
+    // the name of a bitwise operator in `bit32` and LuaJIT's `bit`
+    pub fn bit_function(&self) -> Option<&'static str> {
+        match *self {
+            Operand::BAnd => Some("band"),
+            Operand::BOr  => Some("bor"),
+            Operand::BXor => Some("bxor"),
+            Operand::Shl  => Some("lshift"),
+            Operand::Shr  => Some("rshift"),
+            _             => None,
         }
     }
 }
@@ -470,19 +2330,27 @@ pub fn get_operand(v: &str) -> Option<(Operand, u8)> {
         "^"   => Some((Operand::Pow, 0)),
         "*"   => Some((Operand::Mul, 1)),
         "/"   => Some((Operand::Div, 1)),
+        "//"  => Some((Operand::IDiv, 1)),
         "%"   => Some((Operand::Mod, 1)),
         "+"   => Some((Operand::Add, 2)),
         "-"   => Some((Operand::Sub, 2)),
-        "=="  => Some((Operand::Equal, 3)),
-        "!="  => Some((Operand::NEqual, 3)),
-        "<"   => Some((Operand::Lt, 4)),
-        ">"   => Some((Operand::Gt, 4)),
-        "<="  => Some((Operand::LtEqual, 4)),
-        ">="  => Some((Operand::GtEqual, 4)),
-        "!"   => Some((Operand::Not, 4)),
-        "and" => Some((Operand::And, 4)),
-        "or"  => Some((Operand::Or, 4)),
-        "++"  => Some((Operand::Append, 4)),
+        // bitwise operators bind like they do in Lua, shifts tightest
+        "<<"  => Some((Operand::Shl, 3)),
+        ">>"  => Some((Operand::Shr, 3)),
+        "&"   => Some((Operand::BAnd, 4)),
+        "~"   => Some((Operand::BXor, 5)),
+        "|"   => Some((Operand::BOr, 6)),
+        "=="  => Some((Operand::Equal, 7)),
+        "!="  => Some((Operand::NEqual, 7)),
+        "<"   => Some((Operand::Lt, 8)),
+        ">"   => Some((Operand::Gt, 8)),
+        "<="  => Some((Operand::LtEqual, 8)),
+        ">="  => Some((Operand::GtEqual, 8)),
+        "!"   => Some((Operand::Not, 8)),
+        "and" => Some((Operand::And, 8)),
+        "or"  => Some((Operand::Or, 8)),
+        "++"  => Some((Operand::Append, 8)),
+        "?"   => Some((Operand::Coalesce, 8)),
         _ => None,
     }
 }
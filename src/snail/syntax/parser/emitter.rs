@@ -0,0 +1,265 @@
+use std::fmt;
+use std::fmt::Write;
+
+use super::lexer::Span;
+use super::{Diagnostic, lua_identifier};
+
+// which snail code each line of generated Lua came from
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    pub lines: Vec<(usize, Span)>,
+}
+
+impl SourceMap {
+    // the span the code on a Lua line belongs to, lines counted from 1
+    pub fn lookup(&self, line: usize) -> Option<Span> {
+        self.lines.iter().take_while(|&&(l, _)| l <= line).last().map(|&(_, span)| span)
+    }
+
+    // every `chunk:line` in a Lua message, with the byte range it covers
+    fn locations(text: &str, chunk: &str) -> Vec<(usize, usize, usize)> {
+        let mut found = Vec::new();
+        let mut start = 0;
+
+        while let Some(i) = text[start ..].find(chunk).map(|i| i + start) {
+            let after  = i + chunk.len();
+            let digits = text[after ..].chars().skip(1).take_while(|c| c.is_ascii_digit()).count();
+
+            if text[after ..].starts_with(':') && digits > 0 {
+                let end = after + 1 + digits;
+                found.push((i, end, text[after + 1 .. end].parse().unwrap()))
+            }
+
+            start = after
+        }
+
+        found
+    }
+
+    // the span of the first Lua position a message mentions
+    pub fn first_span(&self, text: &str, chunk: &str) -> Option<Span> {
+        SourceMap::locations(text, chunk).first().and_then(|&(_, _, line)| self.lookup(line))
+    }
+
+    // points Lua positions in a message or traceback at the snail source instead
+    pub fn rewrite(&self, text: &str, chunk: &str, path: &str) -> String {
+        let mut out  = String::new();
+        let mut last = 0;
+
+        for (start, end, line) in SourceMap::locations(text, chunk) {
+            out.push_str(&text[last .. start]);
+
+            match self.lookup(line) {
+                Some(span) => out.push_str(&format!("{}:{}:{}", path, span.start.line, span.start.col + 1)),
+                None       => out.push_str(&text[start .. end]),
+            }
+
+            last = end
+        }
+
+        out.push_str(&text[last ..]);
+        out
+    }
+}
+
+// the Lua version generated code has to run on
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Target {
+    Lua51,
+    // what snail itself runs
+    #[default]
+    Lua52,
+    Lua53,
+    Lua54,
+    LuaJIT,
+}
+
+impl Target {
+    pub fn all() -> [Target; 5] {
+        [Target::Lua51, Target::Lua52, Target::Lua53, Target::Lua54, Target::LuaJIT]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Target::Lua51  => "5.1",
+            Target::Lua52  => "5.2",
+            Target::Lua53  => "5.3",
+            Target::Lua54  => "5.4",
+            Target::LuaJIT => "luajit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Target> {
+        Target::all().iter().find(|t| t.name().eq_ignore_ascii_case(name)).cloned()
+    }
+
+    // 5.3 added an integer subtype, `//` and the bitwise operators
+    pub fn has_integers(&self) -> bool {
+        matches!(*self, Target::Lua53 | Target::Lua54)
+    }
+
+    // where bitwise operators come from when they aren't operators
+    pub fn bit_library(&self) -> Option<&'static str> {
+        match *self {
+            Target::Lua52  => Some("bit32"),
+            Target::LuaJIT => Some("bit"),
+            _              => None,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Target::LuaJIT => write!(f, "LuaJIT"),
+            t              => write!(f, "Lua {}", t.name()),
+        }
+    }
+}
+
+// how generated Lua is laid out and what it runs on
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    // precedes statements with `-- snail: file:line` comments
+    pub comments: bool,
+    pub target:   Target,
+}
+
+// collects generated Lua, keeping count of lines so spans can be mapped to them
+// and indenting every line by the depth it's written at
+#[derive(Default)]
+pub struct Emitter {
+    pub out:     String,
+    pub map:     SourceMap,
+    pub errors:  Vec<Diagnostic>,
+    pub options: Options,
+    file:        String,
+    line:        usize,
+    depth:       usize,
+    fresh:       bool,
+    commented:   usize,
+    readable:    bool,
+}
+
+impl Emitter {
+    // `file` names the snail source in comments
+    pub fn with_options(options: Options, file: &str) -> Emitter {
+        Emitter {
+            options,
+            file:  file.to_owned(),
+            line:  1,
+            fresh: true,
+            ..Emitter::default()
+        }
+    }
+
+    // for messages, where names are shown the way they were written
+    pub fn readable() -> Emitter {
+        Emitter {
+            readable: true,
+            ..Emitter::with_options(Options::default(), "")
+        }
+    }
+
+    // the name a snail identifier goes by in the output
+    pub fn name(&self, name: &str) -> String {
+        if self.readable {
+            name.to_owned()
+        } else {
+            lua_identifier(name)
+        }
+    }
+
+    // constructs the target can't run are reported and emission carries on
+    pub fn reject(&mut self, span: Span, code: &'static str, message: &str) {
+        let error = Diagnostic::error(code, message)
+            .with_span(span)
+            .with_note(&format!("compiling for {}, choose another with --target", self.options.target));
+
+        self.errors.push(error)
+    }
+
+    // Lua for a piece of code that's put together before it's written, like an
+    // arm's checks, keeping whatever the target rejects in it
+    pub fn fragment<F: FnOnce(&mut Emitter) -> fmt::Result>(&mut self, emit: F) -> Result<String, fmt::Error> {
+        let mut inner = Emitter {
+            readable: self.readable,
+            ..Emitter::with_options(self.options.clone(), &self.file)
+        };
+
+        emit(&mut inner)?;
+
+        self.errors.append(&mut inner.errors);
+        Ok(inner.out)
+    }
+
+    // where the next write goes, to come back to with `blank_line_at`
+    pub fn here(&self) -> (usize, usize) {
+        (self.out.len(), self.line)
+    }
+
+    // puts an empty line in at an earlier start of a line, moving the code
+    // mapped from there on down with it
+    pub fn blank_line_at(&mut self, (offset, line): (usize, usize)) {
+        self.out.insert(offset, '\n');
+        self.line += 1;
+
+        for &mut (ref mut l, _) in self.map.lines.iter_mut() {
+            if *l >= line {
+                *l += 1
+            }
+        }
+    }
+
+    pub fn indent(&mut self) {
+        self.depth += 1
+    }
+
+    pub fn dedent(&mut self) {
+        self.depth -= 1
+    }
+
+    // code emitted from here on comes from the given span
+    pub fn mark(&mut self, span: Span) {
+        if let Some(&mut (line, ref mut last)) = self.map.lines.last_mut() {
+            if line == self.line {
+                *last = span;
+                return
+            }
+        }
+
+        self.map.lines.push((self.line, span))
+    }
+
+    // starts a statement on a fresh line, commenting where it came from
+    pub fn statement(&mut self, span: Span) -> fmt::Result {
+        if self.options.comments && span.start.line != self.commented {
+            self.commented = span.start.line;
+            writeln!(self, "-- snail: {}:{}", self.file.clone(), span.start.line)?
+        }
+
+        self.mark(span);
+        Ok(())
+    }
+}
+
+impl fmt::Write for Emitter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for line in s.split_inclusive('\n') {
+            if self.fresh && line != "\n" {
+                for _ in 0 .. self.depth {
+                    self.out.push_str("  ")
+                }
+            }
+
+            self.out.push_str(line);
+            self.fresh = line.ends_with('\n');
+
+            if self.fresh {
+                self.line += 1
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+// globals the generated code calls, which a snail definition mustn't hide
+const GLOBALS: &[&str] = &["bit", "bit32", "math", "select", "type"];
+
+// names that are already valid Lua, can't be mistaken for a mangled one and
+// don't shadow anything the generated code relies on
+fn is_plain(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    let valid = match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _                                              => false,
+    };
+
+    valid && !KEYWORDS.contains(&name) && !GLOBALS.contains(&name) && !name.contains("__")
+}
+
+// plain names are kept as they are. Anything else is prefixed with `__`, which
+// plain names never contain, and escaped so it can be read back:
+// `_` is `_u`, `?` is `_q`, `'` is `_p` and other characters are `_x<hex>_`
+pub fn lua_identifier(name: &str) -> String {
+    if is_plain(name) {
+        return name.to_owned()
+    }
+
+    let mut mangled = String::from("__");
+
+    for c in name.chars() {
+        match c {
+            '_'                           => mangled.push_str("_u"),
+            '?'                           => mangled.push_str("_q"),
+            '\''                          => mangled.push_str("_p"),
+            c if c.is_ascii_alphanumeric() => mangled.push(c),
+            c                             => mangled.push_str(&format!("_x{:x}_", c as u32)),
+        }
+    }
+
+    mangled
+}
+
+// the snail name a Lua identifier was mangled from, if it was mangled at all
+pub fn snail_identifier(lua: &str) -> Option<String> {
+    if !lua.starts_with("__") {
+        return None
+    }
+
+    let mut name  = String::new();
+    let mut chars = lua[2 ..].chars();
+
+    while let Some(c) = chars.next() {
+        if c != '_' {
+            name.push(c);
+            continue
+        }
+
+        match chars.next()? {
+            'u' => name.push('_'),
+            'q' => name.push('?'),
+            'p' => name.push('\''),
+            'x' => {
+                let hex: String = chars.by_ref().take_while(|&c| c != '_').collect();
+                name.push(::std::char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?)
+            },
+            _ => return None,
+        }
+    }
+
+    // generated names like `__args` look mangled but don't round-trip
+    if lua_identifier(&name) == lua {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+// rewrites every mangled identifier in a Lua message back to its snail name
+pub fn demangle(text: &str) -> String {
+    let mut out  = String::new();
+    let mut word = String::new();
+
+    for c in text.chars().chain(Some('\0')) {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+            continue
+        }
+
+        match snail_identifier(&word) {
+            Some(name) => out.push_str(&name),
+            None       => out.push_str(&word),
+        }
+
+        word.clear();
+
+        if c != '\0' {
+            out.push(c)
+        }
+    }
+
+    out
+}
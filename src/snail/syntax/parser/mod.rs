@@ -1,12 +1,15 @@
-pub mod error;
 pub mod ast;
 pub mod traveler;
 pub mod parser;
+pub mod emitter;
+pub mod mangle;
 
 pub use super::lexer;
-pub use self::error::*;
+pub use super::diagnostic::*;
 pub use self::ast::*;
 pub use self::traveler::*;
 pub use self::parser::*;
+pub use self::emitter::*;
+pub use self::mangle::*;
 
-pub type ParserResult<T> = Result<T, ParserError>;
+pub type ParserResult<T> = Result<T, Box<Diagnostic>>;
@@ -15,6 +15,10 @@ impl Parser {
         }
     }
 
+    fn error(&self, code: &'static str, message: &str) -> Box<Diagnostic> {
+        Box::new(Diagnostic::error(code, message).with_span(self.traveler.current().span()))
+    }
+
     pub fn parse(&mut self) -> ParserResult<Vec<Statement>> {
         let mut stack = Vec::new();
         while self.traveler.remaining() > 1 {
@@ -26,6 +30,84 @@ impl Parser {
     }
 
     fn types(&mut self) -> ParserResult<Option<Type>> {
+        let mut params = Vec::new();
+
+        if self.traveler.current_content() != "->" {
+            match self.type_atom()? {
+                Some(Type::Data(name, _)) => {
+                    let mut args = Vec::new();
+
+                    while let Some(t) = self.type_atom()? {
+                        args.push(t)
+                    }
+
+                    params.push(Type::Data(name, Rc::new(args)))
+                },
+                Some(t) => params.push(t),
+                None    => return Ok(None),
+            }
+
+            loop {
+                match self.traveler.current_content().as_str() {
+                    "," => {
+                        self.traveler.next();
+
+                        match self.type_atom()? {
+                            Some(t) => params.push(t),
+                            None    => return Err(self.error("E0001", &format!("expected type, found: {}", self.traveler.current_content()))),
+                        }
+                    },
+                    "|" => {
+                        self.traveler.next();
+
+                        let member = match self.type_atom()? {
+                            Some(t) => t,
+                            None    => return Err(self.error("E0001", &format!("expected type, found: {}", self.traveler.current_content()))),
+                        };
+
+                        let last = params.pop().unwrap();
+                        params.push(Type::union(vec![last, member]))
+                    },
+                    _ => break,
+                }
+            }
+        }
+
+        if self.traveler.current_content() == "->" {
+            self.traveler.next();
+
+            let ret = match self.types()? {
+                Some(t) => t,
+                None    => return Err(self.error("E0001", &format!("expected return type, found: {}", self.traveler.current_content()))),
+            };
+
+            let arity = params.len();
+
+            return Ok(Some(Type::Block(Rc::new(Signature::new(params, arity, false, ret)))))
+        }
+
+        if params.len() > 1 {
+            return Err(self.error("E0001", &format!("expected '->' after parameter types, found: {}", self.traveler.current_content())))
+        }
+
+        Ok(params.pop())
+    }
+
+    fn type_atom(&mut self) -> ParserResult<Option<Type>> {
+        let mut t = match self.type_atom_inner()? {
+            Some(t) => t,
+            None    => return Ok(None),
+        };
+
+        while self.traveler.current_content() == "?" {
+            self.traveler.next();
+            t = Type::Option(Rc::new(t))
+        }
+
+        Ok(Some(t))
+    }
+
+    fn type_atom_inner(&mut self) -> ParserResult<Option<Type>> {
         match self.traveler.current().token_type {
             TokenType::Type   => {
                 let t = get_type(&self.traveler.current_content()).unwrap();
@@ -33,10 +115,51 @@ impl Parser {
 
                 Ok(Some(t))
             },
+            TokenType::Identifier => {
+                let content = self.traveler.current_content();
+                self.traveler.next();
+
+                let name = content.trim_end_matches('?');
+
+                let mut t = match get_type(name) {
+                    Some(t)                  => t,
+                    None if is_variant(name) => Type::Data(Rc::new(name.to_owned()), Rc::new(Vec::new())),
+                    None                     => Type::Generic(Rc::new(name.to_owned())),
+                };
+
+                for _ in name.len() .. content.len() {
+                    t = Type::Option(Rc::new(t))
+                }
+
+                Ok(Some(t))
+            },
+            TokenType::Symbol => {
+                let close = match self.traveler.current_content().as_str() {
+                    "[" => "]",
+                    "(" => ")",
+                    _   => return Ok(None),
+                };
+
+                self.traveler.next();
+
+                let t = match self.types()? {
+                    Some(t) => t,
+                    None    => return Err(self.error("E0001", &format!("expected type, found: {}", self.traveler.current_content()))),
+                };
+
+                self.traveler.expect_content(close)?;
+                self.traveler.next();
+
+                if close == "]" {
+                    Ok(Some(Type::List(Rc::new(t))))
+                } else {
+                    Ok(Some(t))
+                }
+            },
             _ => Ok(None),
         }
     }
-    
+
     pub fn skip_whitespace(&mut self) -> ParserResult<()> {
         while self.traveler.current_content() == "\n" || self.traveler.current().token_type == TokenType::EOL {
             self.traveler.next();
@@ -49,8 +172,113 @@ impl Parser {
         Ok(())
     }
     
+    fn literal(&mut self) -> ParserResult<Option<Expression>> {
+        let span = self.traveler.current().span();
+
+        let a = match self.traveler.current().token_type {
+            TokenType::IntLiteral    => self.int()?,
+            TokenType::FloatLiteral  => Expression::Float(self.traveler.current_content().parse::<f64>().unwrap(), span),
+            TokenType::BoolLiteral   => Expression::Bool(self.traveler.current_content() == "true", span),
+            TokenType::NilLiteral    => Expression::Nil(span),
+            TokenType::StringLiteral => Expression::Str(Rc::new(self.traveler.current_content().clone()), span),
+            _                        => return Ok(None),
+        };
+
+        self.traveler.next();
+
+        Ok(Some(a))
+    }
+
+    fn int(&mut self) -> ParserResult<Expression> {
+        match self.traveler.current_content().parse::<i64>() {
+            Ok(n)  => Ok(Expression::Int(n, self.traveler.current().span())),
+            Err(_) => Err(self.error("E0002", &format!("integer literal out of range: {}", self.traveler.current_content()))),
+        }
+    }
+
+    // '|' opens arms at the start of a line and is bitwise or anywhere else
+    fn is_operator(&self) -> bool {
+        if self.traveler.current_content() == "|" {
+            return match self.traveler.behind() {
+                Some(t) => t.token_type != TokenType::EOL,
+                None    => false,
+            }
+        }
+
+        self.traveler.current().token_type == TokenType::Operator
+    }
+
+    fn pattern(&mut self, close: &str) -> ParserResult<Expression> {
+        if let Some(a) = self.literal()? {
+            return Ok(a)
+        }
+
+        match self.traveler.current().token_type {
+            TokenType::Symbol if self.traveler.current_content() == "(" => {
+                self.traveler.next();
+
+                let a = self.pattern(")")?;
+
+                self.traveler.expect_content(")")?;
+                self.traveler.next();
+
+                Ok(a)
+            },
+            TokenType::Identifier if is_variant(&self.traveler.current_content()) => {
+                let name = Rc::new(self.traveler.current_content());
+                let span = self.traveler.current().span();
+                self.traveler.next();
+
+                let mut fields = Vec::new();
+
+                while self.traveler.current_content() != close {
+                    fields.push(Rc::new(self.pattern(close)?));
+
+                    if self.traveler.remaining() < 2 {
+                        break
+                    }
+                }
+
+                let span = fields.last().map_or(span, |f| span.to(f.span()));
+
+                Ok(Expression::Variant(name, fields, span))
+            },
+            TokenType::Identifier => {
+                let name = Rc::new(self.traveler.current_content().clone());
+                let span = self.traveler.current().span();
+                self.traveler.next();
+
+                let t = if self.traveler.current_content() == ":" {
+                    self.traveler.next();
+
+                    match self.type_atom()? {
+                        Some(t) => Some(t),
+                        None    => return Err(self.error("E0001", &format!("expected type for '{}', found: {}", name, self.traveler.current_content()))),
+                    }
+                } else {
+                    None
+                };
+
+                let default = if self.traveler.current_content() == "=" {
+                    self.traveler.next();
+
+                    match self.literal()? {
+                        Some(d) => Some(Rc::new(d)),
+                        None    => return Err(self.error("E0001", &format!("expected literal default for '{}', found: {}", name, self.traveler.current_content()))),
+                    }
+                } else {
+                    None
+                };
+
+                Ok(Expression::Param { name, t, default, span, })
+            },
+            _ => Err(self.error("E0001", &format!("expected identifier, variant, str, num or bool, found: {}", self.traveler.current_content())))
+        }
+    }
+
     pub fn arm(&mut self) -> ParserResult<Expression> {
         self.traveler.expect_content("|")?;
+        let start = self.traveler.current().span();
         self.traveler.next();
 
         let mut params = Vec::new();
@@ -58,34 +286,7 @@ impl Parser {
         while self.traveler.current_content() != "|" {
             self.skip_whitespace()?;
 
-            let a = match self.traveler.current().token_type {
-                TokenType::IntLiteral    => {
-                    let a = Expression::Number(self.traveler.current_content().parse::<f64>().unwrap());
-                    self.traveler.next();
-                    a
-                }
-                TokenType::FloatLiteral  => {
-                    let a = Expression::Number(self.traveler.current_content().parse::<f64>().unwrap());
-                    self.traveler.next(); 
-                    a
-                }
-                TokenType::BoolLiteral   => {
-                    let a = Expression::Bool(self.traveler.current_content() == "true");
-                    self.traveler.next();
-                    a
-                }
-                TokenType::StringLiteral => {
-                    let a = Expression::Str(Rc::new(self.traveler.current_content().clone()));
-                    self.traveler.next();
-                    a
-                }
-                TokenType::Identifier => {
-                    let a = Expression::Identifier(Rc::new(self.traveler.current_content().clone()));
-                    self.traveler.next();
-                    a
-                }
-                _ => return Err(ParserError::new_pos(self.traveler.current().position, &format!("expected identifier, str, num or bool, found: {}", self.traveler.current_content())))
-            };
+            let a = self.pattern("|")?;
             
             params.push(Rc::new(a));
 
@@ -97,7 +298,9 @@ impl Parser {
         self.traveler.next();
         let body = self.expression()?;
         
-        Ok(Expression::Arm(params, Rc::new(body)))
+        let span = start.to(body.span());
+
+        Ok(Expression::Arm(params, Rc::new(body), span))
     }
 
     pub fn term(&mut self) -> ParserResult<Expression> {
@@ -107,33 +310,34 @@ impl Parser {
             return Ok(Expression::EOF)
         }
 
+        let span = self.traveler.current().span();
+
         match self.traveler.current().token_type {
-            TokenType::IntLiteral    => {
-                let a = Ok(Expression::Number(self.traveler.current_content().parse::<f64>().unwrap()));
-                a
-            }
+            TokenType::IntLiteral    => self.int(),
             TokenType::FloatLiteral  => {
-                let a = Ok(Expression::Number(self.traveler.current_content().parse::<f64>().unwrap()));
+                let a = Ok(Expression::Float(self.traveler.current_content().parse::<f64>().unwrap(), span));
                 a
             }
             TokenType::BoolLiteral   => {
-                let a = Ok(Expression::Bool(self.traveler.current_content() == "true"));
+                let a = Ok(Expression::Bool(self.traveler.current_content() == "true", span));
                 a
             }
+            TokenType::NilLiteral    => Ok(Expression::Nil(span)),
             TokenType::StringLiteral => {
-                let a = Ok(Expression::Str(Rc::new(self.traveler.current_content().clone())));
+                let a = Ok(Expression::Str(Rc::new(self.traveler.current_content().clone()), span));
                 a
             }
             TokenType::Symbol => match self.traveler.current_content().as_str() {
                 "{" => {
                     self.traveler.next();
-                    self.block()
+                    self.block(span)
                 },
                 "|" => self.arm(),
+                "[" => self.record(),
                 "(" => {
                     self.traveler.next();
                     if self.traveler.current_content() == ")" {
-                        return Err(ParserError::new_pos(self.traveler.current().position, &format!("illegal empty clause '()'")))
+                        return Err(self.error("E0001", "illegal empty clause '()'"))
                     }
                     
                     let expr = self.expression()?;
@@ -145,14 +349,16 @@ impl Parser {
                         TokenType::IntLiteral |
                         TokenType::FloatLiteral |
                         TokenType::BoolLiteral |
+                        TokenType::NilLiteral |
                         TokenType::StringLiteral |
                         TokenType::Identifier |
                         TokenType::Symbol => {
                             if self.traveler.current().token_type == TokenType::Symbol {
                                 match self.traveler.current_content().as_str() {
                                     "!"  => {
+                                        let span = expr.span().to(self.traveler.current().span());
                                         self.traveler.next();
-                                        return Ok(Expression::Call(Rc::new(expr), Rc::new(vec!())));
+                                        return Ok(Expression::Call(Rc::new(expr), Rc::new(vec!()), span));
                                     },
                                     "(" => {
                                         let call = self.call(expr)?;
@@ -164,9 +370,11 @@ impl Parser {
                                         self.traveler.next();
                                         let expr_right = self.expression()?;
 
-                                        return Ok(Expression::Assignment(Rc::new(expr), Rc::new(expr_right)))
+                                        let span = expr.span().to(expr_right.span());
+
+                                        return Ok(Expression::Assignment(Rc::new(expr), Rc::new(expr_right), span))
                                     },
-                                    _ => return Err(ParserError::new_pos(self.traveler.current().position, &format!("unexpected symbol: {}", self.traveler.current_content()))),
+                                    _ => return Err(self.error("E0001", &format!("unexpected symbol: {}", self.traveler.current_content()))),
                                 }
                             }
                             let call = self.call(expr)?;
@@ -179,17 +387,28 @@ impl Parser {
 
                     Ok(expr)
                 },
-                _ => Err(ParserError::new_pos(self.traveler.current().position, &format!("unexpected symbol: {}", self.traveler.current_content()))),
+                _ => Err(self.error("E0001", &format!("unexpected symbol: {}", self.traveler.current_content()))),
             },
             TokenType::Identifier => {
-                let id = Expression::Identifier(Rc::new(self.traveler.current_content()));
+                let mut id = Expression::Identifier(Rc::new(self.traveler.current_content()), self.traveler.current().span());
                 self.traveler.next();
+
+                while self.traveler.current_content() == "." {
+                    self.traveler.next();
+
+                    let name = Rc::new(self.traveler.expect(TokenType::Identifier)?);
+                    let span = id.span().to(self.traveler.current().span());
+                    self.traveler.next();
+
+                    id = Expression::Field(Rc::new(id), name, span)
+                }
                 
                 if self.traveler.remaining() > 1 {
                     match self.traveler.current().token_type {
                         TokenType::IntLiteral |
                         TokenType::FloatLiteral |
                         TokenType::BoolLiteral |
+                        TokenType::NilLiteral |
                         TokenType::StringLiteral |
                         TokenType::Identifier => {
                             let call = self.call(id)?;
@@ -198,21 +417,23 @@ impl Parser {
                         },
 
                         TokenType::Symbol => match self.traveler.current_content().as_str() {
-                            "}" | "|" | "," | ")" => Ok(id),
+                            "}" | "|" | "," | ")" | "]" => Ok(id),
                             "(" | "{" => Ok(self.call(id)?),
                             "!"       => {
+                                let span = id.span().to(self.traveler.current().span());
                                 self.traveler.next();
                                 
-                                Ok(Expression::Call(Rc::new(id), Rc::new(vec!())))
+                                Ok(Expression::Call(Rc::new(id), Rc::new(vec!()), span))
                             },
                             "="       => {                            
                                 self.traveler.next();
                                 let expr = self.expression()?;
+                                let span = id.span().to(expr.span());
 
-                                Ok(Expression::Assignment(Rc::new(id), Rc::new(expr)))
+                                Ok(Expression::Assignment(Rc::new(id), Rc::new(expr), span))
                             },
 
-                            _ => Err(ParserError::new_pos(self.traveler.current().position, &format!("unexpected: {}", self.traveler.current_content()))),
+                            _ => Err(self.error("E0001", &format!("unexpected: {}", self.traveler.current_content()))),
                         },
                         _ => Ok(id),
                     }
@@ -220,15 +441,15 @@ impl Parser {
                     Ok(id)
                 }
             },
-            _ => Err(ParserError::new_pos(self.traveler.current().position, &format!("unexpected: {}", self.traveler.current_content()))),
+            _ => Err(self.error("E0001", &format!("unexpected: {}", self.traveler.current_content()))),
         }
     }
     
-    fn block(&mut self) -> ParserResult<Expression> {
+    fn block(&mut self, start: Span) -> ParserResult<Expression> {
         self.skip_whitespace()?;
 
         if self.traveler.current_content() == "}" {
-            return Err(ParserError::new_pos(self.traveler.current().position, &format!("illegal empty clause '{{}}'")))
+            return Err(self.error("E0001", "illegal empty clause '{}'"))
         }
 
         let mut nests = 1;
@@ -250,12 +471,13 @@ impl Parser {
             self.traveler.next();
         }
 
+        let span = start.to(self.traveler.current().span());
         self.traveler.next();
         
         let traveler   = Traveler::new(body);
         let mut parser = Parser::new(traveler);
         
-        Ok(Expression::Block(parser.parse()?))
+        Ok(Expression::Block(parser.parse()?, span))
     }
 
     fn expression(&mut self) -> ParserResult<Expression> {
@@ -268,15 +490,21 @@ impl Parser {
         }
         
         match expr {
-            Expression::Number(_) |
-            Expression::Str(_)    |
-            Expression::Bool(_) => {self.traveler.next();},
+            Expression::Int(..)   |
+            Expression::Float(..) |
+            Expression::Str(..)   |
+            Expression::Bool(..)  |
+            Expression::Nil(_) => {self.traveler.next();},
             _ => (),
         }
 
         if self.traveler.remaining() > 1 {
+            if let Expression::Arm(..) = expr {
+                return Ok(expr)
+            }
+
             self.skip_whitespace()?;
-            if self.traveler.current().token_type == TokenType::Operator {
+            if self.is_operator() {
                 return self.operation(expr)
             }
         }
@@ -294,8 +522,31 @@ impl Parser {
                 },
                 _ => Ok(Statement::Expression(Rc::new(self.expression()?))),
             },
+            TokenType::Identifier if self.traveler.current_content() == "type" && self.traveler.peek().is_some_and(|t| is_variant(t.content())) => {
+                self.data()
+            },
+            TokenType::Identifier if self.traveler.current_content() == "global" && self.traveler.peek().is_some_and(|t| t.token_type == TokenType::Identifier) => {
+                self.traveler.next();
+
+                match self.statement()? {
+                    Statement::Definition(b, t, id, e @ Some(_)) => Ok(Statement::Definition(Binding { global: true, ..b }, t, id, e)),
+                    _ => Err(self.error("E0001", "expected definition after 'global'")),
+                }
+            },
+            TokenType::Identifier if self.traveler.current_content() == "mut" && self.traveler.peek().is_some_and(|t| t.token_type == TokenType::Identifier) => {
+                self.traveler.next();
+
+                match self.statement()? {
+                    Statement::Definition(b, t, id, e) => Ok(Statement::Definition(Binding { mutable: true, ..b }, t, id, e)),
+                    _ => Err(self.error("E0001", "expected definition after 'mut'")),
+                }
+            },
+            TokenType::Identifier if self.traveler.current_content() == "interface" && self.traveler.peek().is_some_and(|t| is_variant(t.content())) => {
+                self.interface()
+            },
             TokenType::Identifier => {
-                let id = self.traveler.current_content();
+                let id      = self.traveler.current_content();
+                let binding = Binding { span: self.traveler.current().span(), ..Binding::default() };
                 self.traveler.next();
                 
                 if self.traveler.current_content() == ":" {
@@ -307,16 +558,16 @@ impl Parser {
                                 self.traveler.next();
                                 let expr = self.expression()?;
                                 
-                                Ok(Statement::Definition(Some(t), Rc::new(id), Some(Rc::new(expr))))
+                                Ok(Statement::Definition(binding, Some(t), Rc::new(id), Some(Rc::new(expr))))
                             },
 
-                            _ => Ok(Statement::Definition(Some(t), Rc::new(id), None)),
+                            _ => Ok(Statement::Definition(binding, Some(t), Rc::new(id), None)),
                         }
                     } else if self.traveler.current_content() == "=" {
                         self.traveler.next();
-                        Ok(Statement::Definition(None, Rc::new(id), Some(Rc::new(self.expression()?))))
+                        Ok(Statement::Definition(binding, None, Rc::new(id), Some(Rc::new(self.expression()?))))
                     } else {
-                        Err(ParserError::new_pos(self.traveler.current().position, &format!("expected '=' or type, found: {}", self.traveler.current_content())))
+                        Err(self.error("E0001", &format!("expected '=' or type, found: {}", self.traveler.current_content())))
                     }
 
                 } else {
@@ -328,6 +579,162 @@ impl Parser {
         }
     }
 
+    fn argument(&mut self) -> ParserResult<Expression> {
+        if self.traveler.current().token_type == TokenType::Identifier {
+            let named = match self.traveler.peek() {
+                Some(t) => t.content() == ":",
+                None    => false,
+            };
+
+            if named {
+                let name  = Rc::new(self.traveler.current_content());
+                let start = self.traveler.current().span();
+                self.traveler.next();
+                self.traveler.next();
+
+                let value = self.expression()?;
+                let span  = start.to(value.span());
+
+                return Ok(Expression::Named(name, Rc::new(value), span))
+            }
+        }
+
+        self.expression()
+    }
+
+    fn data(&mut self) -> ParserResult<Statement> {
+        let mut span = self.traveler.current().span();
+        self.traveler.next();
+
+        let name = Rc::new(self.traveler.current_content());
+        self.traveler.next();
+
+        let mut params = Vec::new();
+
+        while self.traveler.current().token_type == TokenType::Identifier {
+            params.push(Rc::new(self.traveler.current_content()));
+            self.traveler.next();
+        }
+
+        self.traveler.expect_content("=")?;
+        self.traveler.next();
+
+        let mut variants = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+
+            if !is_variant(&self.traveler.expect(TokenType::Identifier)?) {
+                return Err(self.error("E0001", &format!("expected variant name, found: {}", self.traveler.current_content())))
+            }
+
+            let variant = Rc::new(self.traveler.current_content());
+            self.traveler.next();
+
+            let mut fields = Vec::new();
+
+            while let Some(t) = self.type_atom()? {
+                fields.push(t)
+            }
+
+            variants.push(Variant { name: variant, fields, });
+
+            if let Some(t) = self.traveler.behind() {
+                span = span.to(t.span())
+            }
+
+            self.skip_whitespace()?;
+
+            if self.traveler.current_content() != "|" {
+                break
+            }
+
+            self.traveler.next();
+        }
+
+        Ok(Statement::Data(Rc::new(DataType { name, params, variants, span, })))
+    }
+
+    fn interface(&mut self) -> ParserResult<Statement> {
+        let mut span = self.traveler.current().span();
+        self.traveler.next();
+
+        let name = Rc::new(self.traveler.current_content());
+        self.traveler.next();
+
+        self.traveler.expect_content("{")?;
+        self.traveler.next();
+
+        let mut members = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+
+            if self.traveler.current_content() == "}" {
+                span = span.to(self.traveler.current().span());
+                self.traveler.next();
+                break
+            }
+
+            if self.traveler.remaining() < 2 {
+                return Err(self.error("E0001", &format!("unclosed interface '{}'", name)))
+            }
+
+            let member = Rc::new(self.traveler.expect(TokenType::Identifier)?);
+            self.traveler.next();
+
+            self.traveler.expect_content(":")?;
+            self.traveler.next();
+
+            match self.types()? {
+                Some(t) => members.push((member, t)),
+                None    => return Err(self.error("E0001", &format!("expected type for member '{}', found: {}", member, self.traveler.current_content()))),
+            }
+        }
+
+        Ok(Statement::Interface(Rc::new(Interface { name, members, span, })))
+    }
+
+    fn record(&mut self) -> ParserResult<Expression> {
+        let start = self.traveler.current().span();
+        self.traveler.next();
+
+        let mut fields = Vec::new();
+
+        loop {
+            self.skip_whitespace()?;
+
+            if self.traveler.current_content() == "]" {
+                break
+            }
+
+            if self.traveler.remaining() < 2 {
+                return Err(self.error("E0001", "unclosed record"))
+            }
+
+            let name = Rc::new(self.traveler.expect(TokenType::Identifier)?);
+            self.traveler.next();
+
+            self.traveler.expect_content(":")?;
+            self.traveler.next();
+
+            fields.push((name, Rc::new(self.expression()?)));
+
+            self.skip_whitespace()?;
+
+            match self.traveler.current_content().as_str() {
+                "," => { self.traveler.next(); },
+                "]" => (),
+                c   => return Err(self.error("E0001", &format!("expected ',' or ']' in record, found: {}", c))),
+            }
+        }
+
+        let span = start.to(self.traveler.current().span());
+        self.traveler.next();
+
+        Ok(Expression::Record(fields, span))
+    }
+
     fn call(&mut self, caller: Expression) -> ParserResult<Expression> {
         let mut args = Vec::new();
 
@@ -336,7 +743,7 @@ impl Parser {
             if self.traveler.current_content() == "," {
                 self.traveler.next();
                 
-                let expr = self.expression()?;
+                let expr = self.argument()?;
 
                 if expr == Expression::EOF {
                     break
@@ -344,7 +751,7 @@ impl Parser {
 
                 args.push(expr);
             } else if acc == 0 {
-                let expr = self.expression()?;
+                let expr = self.argument()?;
                 
                 if expr == Expression::EOF {
                     break
@@ -362,7 +769,9 @@ impl Parser {
             acc += 1;
         }
         
-        Ok(Expression::Call(Rc::new(caller), Rc::new(args)))
+        let span = args.last().map_or(caller.span(), |a| caller.span().to(a.span()));
+
+        Ok(Expression::Call(Rc::new(caller), Rc::new(args), span))
     }
     
     fn operation(&mut self, expression: Expression) -> ParserResult<Expression> {
@@ -379,9 +788,11 @@ impl Parser {
         let term = self.term()?;
         
         match term {
-            Expression::Number(_) |
-            Expression::Str(_)    |
-            Expression::Bool(_) => {self.traveler.next();},
+            Expression::Int(..)   |
+            Expression::Float(..) |
+            Expression::Str(..)   |
+            Expression::Bool(..)  |
+            Expression::Nil(_) => {self.traveler.next();},
             _ => (),
         }
 
@@ -391,12 +802,13 @@ impl Parser {
         
         while ex_stack.len() > 1 {
             if !done {
-                if self.traveler.current().token_type != TokenType::Operator {
+                if !self.is_operator() {
                     match self.traveler.current().token_type {
                         TokenType::IntLiteral |
                         TokenType::FloatLiteral |
                         TokenType::StringLiteral    |
-                        TokenType::BoolLiteral => {self.traveler.next();},
+                        TokenType::BoolLiteral |
+                        TokenType::NilLiteral => {self.traveler.next();},
                         _ => (),
                     }
                     done = true;
@@ -406,44 +818,39 @@ impl Parser {
                 let (op, precedence) = get_operand(&self.traveler.current_content()).unwrap();
                 self.traveler.next();
 
-                if precedence >= op_stack.last().unwrap().1 {
+                // everything waiting that binds at least as tight goes first
+                while op_stack.last().is_some_and(|&(_, p)| precedence >= p) {
                     let left  = ex_stack.pop().unwrap();
                     let right = ex_stack.pop().unwrap();
 
                     ex_stack.push(Expression::Operation {
+                        span:  right.span().to(left.span()),
                         right: Rc::new(left),
                         op:    op_stack.pop().unwrap().0,
                         left:  Rc::new(right)
                     });
-
-                    let term = self.term()?;
-                    match term {
-                        Expression::Number(_) |
-                        Expression::Str(_)    |
-                        Expression::Bool(_) => {self.traveler.next();},
-                        _ => (),
-                    }
-                    ex_stack.push(term);
-                    op_stack.push((op, precedence));
-
-                    continue
                 }
 
                 let term = self.term()?;
                 match term {
-                    Expression::Number(_) |
-                    Expression::Str(_)    |
-                    Expression::Bool(_) => {self.traveler.next();},
+                    Expression::Int(..)   |
+                    Expression::Float(..) |
+                    Expression::Str(..)   |
+                    Expression::Bool(..)  |
+                    Expression::Nil(_) => {self.traveler.next();},
                     _ => (),
                 }
                 ex_stack.push(term);
                 op_stack.push((op, precedence));
+
+                continue
             }
 
             let left  = ex_stack.pop().unwrap();
             let right = ex_stack.pop().unwrap();
 
             ex_stack.push(Expression::Operation {
+                span:  right.span().to(left.span()),
                 right: Rc::new(left),
                 op:    op_stack.pop().unwrap().0,
                 left:  Rc::new(right)
@@ -453,3 +860,7 @@ impl Parser {
         Ok(ex_stack.pop().unwrap())
     }
 }
+
+fn is_variant(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_uppercase())
+}
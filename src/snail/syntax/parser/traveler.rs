@@ -1,5 +1,5 @@
 use super::lexer::{Token, TokenType};
-use super::{ParserError, ParserResult};
+use super::{Diagnostic, ParserResult};
 
 #[derive(Debug, Clone)]
 pub struct Traveler {
@@ -44,6 +44,18 @@ impl Traveler {
         &self.tokens[self.top]
     }
 
+    pub fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.top + 1)
+    }
+
+    pub fn behind(&self) -> Option<&Token> {
+        if self.top > 0 {
+            self.tokens.get(self.top - 1)
+        } else {
+            None
+        }
+    }
+
     pub fn get(&self, i: usize) -> &Token {
         assert!(i > 0 && i < self.tokens.len(), "trying to get non-existing token");
         &self.tokens[i]
@@ -57,7 +69,7 @@ impl Traveler {
         if self.current().token_type == token {
             Ok(self.current_content())
         } else {
-            Err(ParserError::new_pos(self.current().position, &format!("expected '{:?}', found '{}'", token, self.current_content())))
+            Err(Box::new(Diagnostic::error("E0001", &format!("expected '{:?}', found '{}'", token, self.current_content())).with_span(self.current().span())))
         }
     }
 
@@ -65,7 +77,7 @@ impl Traveler {
         if &self.current_content() == content {
             Ok(self.current_content())
         } else {
-            Err(ParserError::new_pos(self.current().position, &format!("expected '{}', found '{}'", content, self.current_content())))
+            Err(Box::new(Diagnostic::error("E0001", &format!("expected '{}', found '{}'", content, self.current_content())).with_span(self.current().span())))
         }
     }
 
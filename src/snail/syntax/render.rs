@@ -0,0 +1,159 @@
+use std::fmt::Write;
+
+use super::lexer::Span;
+use super::diagnostic::{Diagnostic, Severity};
+
+#[derive(Clone, Copy)]
+enum Style {
+    Error,
+    Warning,
+    Gutter,
+    Bold,
+}
+
+impl Style {
+    fn code(&self) -> &'static str {
+        match *self {
+            Style::Error   => "\x1b[1;31m",
+            Style::Warning => "\x1b[1;33m",
+            Style::Gutter  => "\x1b[1;34m",
+            Style::Bold    => "\x1b[1m",
+        }
+    }
+
+    fn of(severity: Severity) -> Style {
+        match severity {
+            Severity::Error   => Style::Error,
+            Severity::Warning => Style::Warning,
+        }
+    }
+}
+
+struct Painter {
+    color: bool,
+}
+
+impl Painter {
+    fn paint(&self, style: Style, text: &str) -> String {
+        if self.color && !text.is_empty() {
+            format!("{}{}\x1b[0m", style.code(), text)
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+// an underlined span, `^` for the primary one and `-` for labels
+struct Mark<'a> {
+    span:    Span,
+    primary: bool,
+    message: &'a str,
+}
+
+// the columns a span covers on its first line, at least one
+fn columns(span: &Span, line: &str) -> (usize, usize) {
+    let width = line.chars().count();
+    let start = span.start.col;
+
+    let end = if span.end.line == span.start.line && span.end.col > start {
+        span.end.col
+    } else if span.end.line > span.start.line {
+        width
+    } else {
+        start + 1
+    };
+
+    (start, end.max(start + 1))
+}
+
+// lays a diagnostic out like rustc does: header, location, the quoted source
+// with the spans underlined, then notes
+pub fn render(diagnostic: &Diagnostic, color: bool) -> String {
+    let painter = Painter { color };
+    let style   = Style::of(diagnostic.severity);
+
+    let mut out = String::new();
+
+    let header = format!("{}[{}]", diagnostic.severity, diagnostic.code);
+    let _ = write!(out, "{}{}", painter.paint(style, &header), painter.paint(Style::Bold, &format!(": {}", diagnostic.message)));
+
+    let mut marks = Vec::new();
+
+    if let Some(span) = diagnostic.span {
+        marks.push(Mark { span, primary: true, message: "" })
+    }
+
+    for l in diagnostic.labels.iter() {
+        marks.push(Mark { span: l.span, primary: false, message: &l.message })
+    }
+
+    let last_line = marks.iter().map(|m| m.span.start.line).max().unwrap_or(0);
+    let gutter    = " ".repeat(format!("{}", last_line).len());
+    let bar       = painter.paint(Style::Gutter, "|");
+
+    match (&diagnostic.source, diagnostic.span) {
+        (Some(source), Some(span)) => {
+            let _ = write!(out, "\n{}{} {}:{}:{}", gutter, painter.paint(Style::Gutter, "-->"), source.path, span.start.line, span.start.col + 1);
+
+            marks.sort_by_key(|m| (m.span.start.line, !m.primary));
+
+            let _ = write!(out, "\n{} {}", gutter, bar);
+
+            let mut previous = None;
+
+            for m in marks.iter() {
+                let number = m.span.start.line;
+
+                let line = match source.line(number) {
+                    Some(l) => l,
+                    None    => continue,
+                };
+
+                if previous != Some(number) {
+                    if previous.is_some_and(|p| number > p + 1) {
+                        let _ = write!(out, "\n{}", painter.paint(Style::Gutter, "..."));
+                    }
+
+                    let _ = write!(out, "\n{} {} {}", painter.paint(Style::Gutter, &format!("{:>1$}", number, gutter.len())), bar, line);
+
+                    previous = Some(number)
+                }
+
+                let (start, end) = columns(&m.span, line);
+
+                // keeps tabs so the underline lines up with the quoted source
+                let indent: String = line.chars().chain(::std::iter::repeat(' ')).take(start).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+
+                let (underline, mark_style) = if m.primary {
+                    ("^".repeat(end - start), style)
+                } else {
+                    ("-".repeat(end - start), Style::Gutter)
+                };
+
+                let message = if m.message.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}", m.message)
+                };
+
+                let _ = write!(out, "\n{} {} {}{}", gutter, bar, indent, painter.paint(mark_style, &format!("{}{}", underline, message)));
+            }
+
+            if !diagnostic.notes.is_empty() {
+                let _ = write!(out, "\n{} {}", gutter, bar);
+            }
+        },
+
+        (Some(source), None) => {
+            let _ = write!(out, "\n{}{} {}", gutter, painter.paint(Style::Gutter, "-->"), source.path);
+        },
+
+        _ => (),
+    }
+
+    for n in diagnostic.notes.iter() {
+        let _ = write!(out, "\n{} {} {}", gutter, painter.paint(Style::Gutter, "="), painter.paint(Style::Bold, &format!("note: {}", n)));
+    }
+
+    out
+}
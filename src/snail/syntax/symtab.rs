@@ -4,9 +4,15 @@ use std::collections::HashMap;
 
 use std::fmt;
 
+use super::parser::Expression;
+
+pub type Arms = Rc<Vec<Vec<Rc<Expression>>>>;
+
 pub struct SymTab {
     parent: Option<Rc<SymTab>>,
     names:  RefCell<HashMap<String, usize>>,
+    arms:   RefCell<HashMap<String, Arms>>,
+    mutable: RefCell<Vec<String>>,
 }
 
 impl SymTab {
@@ -20,6 +26,8 @@ impl SymTab {
         SymTab {
             parent: Some(parent),
             names:  RefCell::new(hash_names),
+            arms:   RefCell::new(HashMap::new()),
+            mutable: RefCell::new(Vec::new()),
         }
     }
 
@@ -27,6 +35,8 @@ impl SymTab {
         SymTab {
             parent: None,
             names:  RefCell::new(HashMap::new()),
+            arms:   RefCell::new(HashMap::new()),
+            mutable: RefCell::new(Vec::new()),
         }
     }
 
@@ -56,6 +66,68 @@ impl SymTab {
         }
     }
 
+    pub fn set_arms(&self, name: &str, arms: Arms) {
+        self.arms.borrow_mut().insert(name.to_string(), arms);
+    }
+
+    pub fn get_arms(&self, name: &str) -> Option<Arms> {
+        if self.names.borrow().contains_key(name) {
+            return self.arms.borrow().get(name).cloned()
+        }
+
+        match self.parent {
+            Some(ref parent) => parent.get_arms(name),
+            None => None,
+        }
+    }
+
+    pub fn set_mutable(&self, name: &str) {
+        self.mutable.borrow_mut().push(name.to_string())
+    }
+
+    pub fn is_mutable(&self, name: &str) -> bool {
+        if self.names.borrow().contains_key(name) {
+            return self.mutable.borrow().iter().any(|n| n == name)
+        }
+
+        match self.parent {
+            Some(ref parent) => parent.is_mutable(name),
+            None => false,
+        }
+    }
+
+    // the closest visible names by edit distance, for "did you mean" hints
+    pub fn suggest(&self, name: &str) -> Vec<String> {
+        let mut visible = Vec::new();
+        self.visible_names(&mut visible);
+
+        let limit = ::std::cmp::max(1, name.chars().count() / 3);
+
+        let mut scored: Vec<(usize, String)> = visible.into_iter()
+            .map(|n| (distance(name, &n), n))
+            .filter(|&(d, _)| d <= limit)
+            .collect();
+
+        scored.sort();
+
+        match scored.first().map(|&(d, _)| d) {
+            Some(best) => scored.into_iter().filter(|&(d, _)| d == best).map(|(_, n)| n).take(3).collect(),
+            None       => Vec::new(),
+        }
+    }
+
+    fn visible_names(&self, acc: &mut Vec<String>) {
+        for n in self.names.borrow().keys() {
+            if !acc.contains(n) {
+                acc.push(n.clone())
+            }
+        }
+
+        if let Some(ref parent) = self.parent {
+            parent.visible_names(acc)
+        }
+    }
+
     pub fn visualize(&self, env_index: usize) {
         if env_index > 0 {
             if let Some(ref p) = self.parent {
@@ -91,3 +163,29 @@ impl fmt::Debug for SymTab {
         Ok(())
     }
 }
+
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut row: Vec<usize> = (0 .. b.len() + 1).collect();
+
+    for i in 1 .. a.len() + 1 {
+        let mut previous = row[0];
+        row[0] = i;
+
+        for j in 1 .. b.len() + 1 {
+            let current = row[j];
+
+            row[j] = if a[i - 1] == b[j - 1] {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(row[j - 1])
+            };
+
+            previous = current
+        }
+    }
+
+    row[b.len()]
+}
@@ -1,14 +1,20 @@
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::fmt;
 
-use super::{RunResult, RunError};
-
-use super::parser::Type;
+use super::lexer::Span;
+use super::parser::{Type, Signature, DataType, Interface};
 
 pub struct TypeTab {
     parent: Option<Rc<TypeTab>>,
     types: RefCell<Vec<Type>>,
+    vars: RefCell<Vec<Option<Type>>>,
+    // where inference bound each variable, for pointing at it when types clash
+    origins: RefCell<Vec<Option<Span>>>,
+    at: Cell<Option<Span>>,
+    data: RefCell<Vec<Rc<DataType>>>,
+    interfaces: RefCell<Vec<Rc<Interface>>>,
 }
 
 impl TypeTab {
@@ -16,6 +22,11 @@ impl TypeTab {
         TypeTab {
             parent: Some(parent),
             types: RefCell::new(types.clone()),
+            vars: RefCell::new(Vec::new()),
+            origins: RefCell::new(Vec::new()),
+            at: Cell::new(None),
+            data: RefCell::new(Vec::new()),
+            interfaces: RefCell::new(Vec::new()),
         }
     }
 
@@ -23,22 +34,15 @@ impl TypeTab {
         TypeTab {
             parent: None,
             types: RefCell::new(Vec::new()),
+            vars: RefCell::new(Vec::new()),
+            origins: RefCell::new(Vec::new()),
+            at: Cell::new(None),
+            data: RefCell::new(Vec::new()),
+            interfaces: RefCell::new(Vec::new()),
         }
     }
 
-    pub fn new_partial(parent: Rc<TypeTab>, types: &[Type], size: usize) -> TypeTab {
-        let mut stack = types.to_vec();
-        for _ in 0 .. size - types.len() {
-            stack.push(Type::Undefined)
-        }
-
-        TypeTab {
-            parent: Some(parent),
-            types: RefCell::new(stack),
-        }
-    }
-
-    pub fn set_type(&self, index: usize, env_index: usize, t: Type) -> RunResult<()> {
+    pub fn set_type(&self, index: usize, env_index: usize, t: Type) -> Result<(), String> {
         if env_index == 0 {
             let mut types = self.types.borrow_mut();
             match types.get_mut(index) {
@@ -46,28 +50,325 @@ impl TypeTab {
                     *v = t;
                     Ok(())
                 },
-                None => Err(RunError::new(&format!("can't set type of invalid type index: {}", index))),
+                None => Err(format!("can't set type of invalid type index: {}", index)),
             }
         } else {
             match self.parent {
                 Some(ref p) => p.set_type(index, env_index - 1, t),
-                None => Err(RunError::new(&format!("can't set type with invalid env index: {}", env_index))),
+                None => Err(format!("can't set type with invalid env index: {}", env_index)),
             }
         }
     }
 
-    pub fn get_type(&self, index: usize, env_index: usize) -> RunResult<Type> {
+    pub fn get_type(&self, index: usize, env_index: usize) -> Result<Type, String> {
         if env_index == 0 {
             match self.types.borrow().get(index) {
                 Some(v) => Ok(v.clone()),
-                None    => Err(RunError::new(&format!("can't get type of invalid type index: {}", index))),
+                None    => Err(format!("can't get type of invalid type index: {}", index)),
             }
         } else {
             match self.parent {
                 Some(ref p) => p.get_type(index, env_index - 1),
-                None => Err(RunError::new(&format!("can't get type with invalid env index: {}", index))),
+                None => Err(format!("can't get type with invalid env index: {}", index)),
+            }
+        }
+    }
+
+    pub fn fresh(&self) -> Type {
+        match self.parent {
+            Some(ref p) => p.fresh(),
+            None => {
+                let mut vars = self.vars.borrow_mut();
+                vars.push(None);
+                self.origins.borrow_mut().push(None);
+                Type::Var(vars.len() - 1)
+            },
+        }
+    }
+
+    fn bind(&self, var: usize, t: Type) {
+        match self.parent {
+            Some(ref p) => p.bind(var, t),
+            None        => {
+                self.vars.borrow_mut()[var] = Some(t);
+                self.origins.borrow_mut()[var] = self.at.get()
+            },
+        }
+    }
+
+    fn set_at(&self, span: Option<Span>) {
+        match self.parent {
+            Some(ref p) => p.set_at(span),
+            None        => self.at.set(span),
+        }
+    }
+
+    // the span that first gave a variable its type, following variables bound
+    // to other variables
+    pub fn origin(&self, t: &Type) -> Option<Span> {
+        match *t {
+            Type::Var(v) => {
+                let own = match self.parent {
+                    Some(ref p) => return p.origin(t),
+                    None        => self.origins.borrow().get(v).cloned().unwrap_or(None),
+                };
+
+                own.or_else(|| self.lookup(v).and_then(|t| self.origin(&t)))
+            },
+            _ => None,
+        }
+    }
+
+    fn lookup(&self, var: usize) -> Option<Type> {
+        match self.parent {
+            Some(ref p) => p.lookup(var),
+            None        => self.vars.borrow().get(var).cloned().unwrap_or(None),
+        }
+    }
+
+    pub fn resolve(&self, t: &Type) -> Type {
+        match *t {
+            Type::Var(v) => match self.lookup(v) {
+                Some(t) => self.resolve(&t),
+                None    => Type::Var(v),
+            },
+            Type::Block(ref sig)     => Type::Block(Rc::new(self.resolve_signature(sig))),
+            Type::Overload(ref sigs) => Type::Overload(Rc::new(sigs.iter().map(|s| self.resolve_signature(s)).collect())),
+            Type::List(ref t)        => Type::List(Rc::new(self.resolve(t))),
+            Type::Option(ref t)      => Type::Option(Rc::new(self.resolve(t))),
+            Type::Union(ref ts)      => Type::union(ts.iter().map(|t| self.resolve(t)).collect()),
+            Type::Data(ref n, ref a) => Type::Data(n.clone(), Rc::new(a.iter().map(|t| self.resolve(t)).collect())),
+            Type::Record(ref fields) => Type::Record(Rc::new(fields.iter().map(|(n, t)| (n.clone(), self.resolve(t))).collect())),
+            ref t                    => t.clone(),
+        }
+    }
+
+    pub fn add_data(&self, data: Rc<DataType>) {
+        self.data.borrow_mut().push(data)
+    }
+
+    pub fn get_variant(&self, name: &str) -> Option<(Rc<DataType>, usize)> {
+        for data in self.data.borrow().iter().rev() {
+            if let Some(i) = data.variants.iter().position(|v| *v.name == name) {
+                return Some((data.clone(), i))
+            }
+        }
+
+        match self.parent {
+            Some(ref p) => p.get_variant(name),
+            None        => None,
+        }
+    }
+
+    pub fn add_interface(&self, interface: Rc<Interface>) {
+        self.interfaces.borrow_mut().push(interface)
+    }
+
+    pub fn get_interface(&self, name: &str) -> Option<Rc<Interface>> {
+        for interface in self.interfaces.borrow().iter().rev() {
+            if *interface.name == name {
+                return Some(interface.clone())
+            }
+        }
+
+        match self.parent {
+            Some(ref p) => p.get_interface(name),
+            None        => None,
+        }
+    }
+
+    // annotations name interfaces like data types; swap in their members
+    pub fn expand(&self, t: &Type) -> Type {
+        match *t {
+            Type::Data(ref n, ref a) if a.is_empty() => match self.get_interface(n) {
+                Some(interface) => Type::Interface(interface),
+                None            => t.clone(),
+            },
+            Type::Block(ref sig)     => Type::Block(Rc::new(self.expand_signature(sig))),
+            Type::Overload(ref sigs) => Type::Overload(Rc::new(sigs.iter().map(|s| self.expand_signature(s)).collect())),
+            Type::List(ref t)        => Type::List(Rc::new(self.expand(t))),
+            Type::Option(ref t)      => Type::Option(Rc::new(self.expand(t))),
+            Type::Union(ref ts)      => Type::union(ts.iter().map(|t| self.expand(t)).collect()),
+            Type::Data(ref n, ref a) => Type::Data(n.clone(), Rc::new(a.iter().map(|t| self.expand(t)).collect())),
+            Type::Record(ref fields) => Type::Record(Rc::new(fields.iter().map(|(n, t)| (n.clone(), self.expand(t))).collect())),
+            ref t                    => t.clone(),
+        }
+    }
+
+    fn expand_signature(&self, sig: &Signature) -> Signature {
+        Signature::new(
+            sig.params.iter().map(|p| self.expand(p)).collect(),
+            sig.required,
+            sig.variadic,
+            self.expand(&sig.ret),
+        ).with_origins(sig.origins.clone())
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.parent.is_none()
+    }
+
+    pub fn instantiate(&self, t: &Type) -> Type {
+        let mut fresh = HashMap::new();
+        self.substitute(t, &mut |g| fresh.entry(g.to_string()).or_insert_with(|| self.fresh()).clone())
+    }
+
+    pub fn generalize(&self, t: &Type, group: &[usize]) -> Type {
+        let t = self.resolve(t);
+
+        let mut bound = Vec::new();
+
+        for (i, other) in self.types.borrow().iter().enumerate() {
+            if !group.contains(&i) {
+                self.free_vars(&self.resolve(other), &mut bound)
             }
         }
+
+        let mut free = Vec::new();
+        self.free_vars(&t, &mut free);
+
+        for v in free {
+            if !bound.contains(&v) {
+                self.bind(v, Type::Generic(Rc::new(format!("t{}", v))))
+            }
+        }
+
+        self.resolve(&t)
+    }
+
+    fn free_vars(&self, t: &Type, acc: &mut Vec<usize>) {
+        match *t {
+            Type::Var(v) if !acc.contains(&v) => acc.push(v),
+            Type::Block(ref sig)     => {
+                for p in sig.params.iter() {
+                    self.free_vars(p, acc)
+                }
+                self.free_vars(&sig.ret, acc)
+            },
+            Type::Overload(ref sigs) => for sig in sigs.iter() {
+                self.free_vars(&Type::Block(Rc::new(sig.clone())), acc)
+            },
+            Type::List(ref t)        |
+            Type::Option(ref t)      => self.free_vars(t, acc),
+            Type::Data(_, ref a)     |
+            Type::Union(ref a)       => for t in a.iter() {
+                self.free_vars(t, acc)
+            },
+            Type::Record(ref fields) => for (_, t) in fields.iter() {
+                self.free_vars(t, acc)
+            },
+            _                        => (),
+        }
+    }
+
+    fn substitute(&self, t: &Type, f: &mut dyn FnMut(&str) -> Type) -> Type {
+        match *t {
+            Type::Generic(ref g)     => f(g),
+            Type::Block(ref sig)     => Type::Block(Rc::new(self.substitute_signature(sig, f))),
+            Type::Overload(ref sigs) => Type::Overload(Rc::new(sigs.iter().map(|s| self.substitute_signature(s, f)).collect())),
+            Type::List(ref t)        => Type::List(Rc::new(self.substitute(t, f))),
+            Type::Option(ref t)      => Type::Option(Rc::new(self.substitute(t, f))),
+            Type::Union(ref ts)      => Type::union(ts.iter().map(|t| self.substitute(t, f)).collect()),
+            Type::Data(ref n, ref a) => Type::Data(n.clone(), Rc::new(a.iter().map(|t| self.substitute(t, f)).collect())),
+            Type::Record(ref fields) => Type::Record(Rc::new(fields.iter().map(|(n, t)| (n.clone(), self.substitute(t, f))).collect())),
+            ref t                    => t.clone(),
+        }
+    }
+
+    fn substitute_signature(&self, sig: &Signature, f: &mut dyn FnMut(&str) -> Type) -> Signature {
+        Signature::new(
+            sig.params.iter().map(|p| self.substitute(p, f)).collect(),
+            sig.required,
+            sig.variadic,
+            self.substitute(&sig.ret, f),
+        ).with_origins(sig.origins.clone())
+    }
+
+    // parameters remember where they were inferred once their variables are gone
+    pub fn resolve_signature(&self, sig: &Signature) -> Signature {
+        Signature::new(
+            sig.params.iter().map(|p| self.resolve(p)).collect(),
+            sig.required,
+            sig.variadic,
+            self.resolve(&sig.ret),
+        ).with_origins(sig.params.iter().enumerate().map(|(i, p)| sig.origin(i).or_else(|| self.origin(p))).collect())
+    }
+
+    // unifies, remembering the span as the origin of every variable it binds
+    pub fn unify_at(&self, a: &Type, b: &Type, span: Span) -> Result<(), String> {
+        self.set_at(Some(span));
+        let result = self.unify(a, b);
+        self.set_at(None);
+
+        result
+    }
+
+    pub fn unify(&self, a: &Type, b: &Type) -> Result<(), String> {
+        let a = self.resolve(a);
+        let b = self.resolve(b);
+
+        match (&a, &b) {
+            (&Type::Var(i), &Type::Var(j)) if i == j => Ok(()),
+            (&Type::Var(i), t) | (t, &Type::Var(i)) => {
+                if t.occurs(i) {
+                    return Err(format!("infinite type: {:?} occurs in {:?}", Type::Var(i), t))
+                }
+
+                self.bind(i, t.clone());
+                Ok(())
+            },
+            (Type::Any, Type::Option(_)) => Err(format!("expected '{:?}', got possibly nil '{:?}'", a, b)),
+            (Type::Any, _) | (_, Type::Any) | (Type::Undefined, _) | (_, Type::Undefined) => Ok(()),
+            (Type::Num, Type::Int) | (Type::Num, Type::Float) | (Type::Float, Type::Int) => Ok(()),
+            (Type::Block(x), Type::Block(y)) => {
+                if x.params.len() != y.params.len() || x.variadic != y.variadic {
+                    return Err(format!("expected '{:?}', got '{:?}'", a, b))
+                }
+
+                for (p, q) in x.params.iter().zip(y.params.iter()) {
+                    self.unify(p, q)?
+                }
+
+                self.unify(&x.ret, &y.ret)
+            },
+            (Type::List(x), Type::List(y))       => self.unify(x, y),
+            (Type::Option(x), Type::Option(y))   => self.unify(x, y),
+            (Type::Union(_), _) if a.compare(&b) => Ok(()),
+            (Type::Option(x), y)                 => self.unify(x, y),
+            (x, Type::Option(_))                 => Err(format!("expected '{:?}', got possibly nil '{:?}'", x, b)),
+            (Type::Data(n, x), Type::Data(m, y)) if n == m && x.len() == y.len() => {
+                for (p, q) in x.iter().zip(y.iter()) {
+                    self.unify(p, q)?
+                }
+
+                Ok(())
+            },
+            (Type::Record(x), Type::Record(y)) => {
+                for (n, t) in x.iter() {
+                    match y.iter().find(|(m, _)| m == n) {
+                        Some((_, u)) => self.unify(t, u)?,
+                        None         => return Err(format!("expected '{:?}', got '{:?}': missing field '{}'", a, b, n)),
+                    }
+                }
+
+                Ok(())
+            },
+            (Type::Interface(i), Type::Record(fields)) => match i.check(fields) {
+                Ok(())  => Ok(()),
+                Err(e)  => Err(format!("'{:?}' doesn't satisfy '{}': {}", b, i.name, e)),
+            },
+            (Type::Interface(_), Type::Interface(_)) if a.compare(&b) => Ok(()),
+            (Type::Block(x), Type::Overload(sigs)) |
+            (Type::Overload(sigs), Type::Block(x)) => {
+                if sigs.iter().any(|s| s.compare(x)) {
+                    Ok(())
+                } else {
+                    Err(format!("no arm of '{:?}' matches '{:?}'", Type::Overload(sigs.clone()), x))
+                }
+            },
+            (a, b) if a == b => Ok(()),
+            (a, b) => Err(format!("expected '{:?}', got '{:?}'", a, b)),
+        }
     }
 
     pub fn visualize(&self, env_index: usize) {